@@ -2,14 +2,14 @@ use log::info;
 use ftswarm::prelude::*;
 
 #[tokio::main]
-async fn main() -> Result<(), String> {
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::builder()
         .filter_level(log::LevelFilter::Info)
         .filter_module("ftswarm_serial", log::LevelFilter::Trace)
         .init();
 
-    // Automatically connects to the first available ftSwarm
-    let swarm = FtSwarm::default();
+    // Connects to the first ftSwarm that answers on a serial port
+    let swarm = FtSwarm::connect_first().await?;
 
     let response = swarm.whoami().await?;
     info!("WhoAmI: {}", response);
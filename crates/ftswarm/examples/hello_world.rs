@@ -12,7 +12,7 @@ aliases! {
 }
 
 #[tokio::main]
-async fn main() -> Result<(), String> {
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::builder()
         .filter_level(log::LevelFilter::Debug)
         .init();
@@ -28,8 +28,8 @@ async fn main() -> Result<(), String> {
         }
     });
 
-    // Automatically connects to the first available ftSwarm
-    let swarm = FtSwarm::default();
+    // Connects to the first ftSwarm that answers on a serial port
+    let swarm = FtSwarm::connect_first().await?;
 
     let response = swarm.whoami().await?;
     info!("WhoAmI: {}", response);
@@ -46,20 +46,17 @@ async fn main() -> Result<(), String> {
     led1.lock().await.set_color(LedColor::blue()).await?;
     led2.lock().await.set_color(LedColor::cyan()).await?;
 
-    let mut switch_state = switch.lock().await.value;
-    loop {
-        let value = switch.lock().await.value;
-
-        if switch_state != value {
-            switch_state = value;
-            info!("Switch state: {}", switch_state);
-
-            let new_led_color = recv_color.recv().await.unwrap();
-            let color = LedColor::hsl(new_led_color, 100, 50);
-            led1.lock().await.set_color(color.clone()).await?;
-            led2.lock().await.set_color(color).await?;
-        }
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+    // Wait for changes of the switch without keeping it locked
+    let mut switch_state = switch.lock().await.watch();
+    while switch_state.changed().await.is_ok() {
+        let value = switch_state.borrow_and_update().value;
+        info!("Switch state: {}", value);
 
+        let new_led_color = recv_color.recv().await.unwrap();
+        let color = LedColor::hsl(new_led_color, 100, 50);
+        led1.lock().await.set_color(color.clone()).await?;
+        led2.lock().await.set_color(color).await?;
     }
-}
\ No newline at end of file
+
+    Ok(())
+}
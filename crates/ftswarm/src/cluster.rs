@@ -0,0 +1,163 @@
+//! Several ftSwarms, each on its own connection, addressed through a single handle
+//!
+//! Ports are named with the hostname of their controller, like `kelda.A1`, so the aliases of a
+//! model can say which controller a port belongs to.
+//!
+//! ```no_run
+//! use ftswarm::prelude::*;
+//! use ftswarm::cluster::SwarmCluster;
+//!
+//! aliases! {
+//!     Ports {
+//!         LIFT_SWITCH = "kelda.A1",
+//!         CRANE_MOTOR = "lisa.M1",
+//!     }
+//! }
+//!
+//! # async fn example() -> Result<(), FtSwarmError> {
+//! let cluster = SwarmCluster::discover(&Discovery::new()).await?;
+//! let switch = cluster.create::<Switch, _>(Ports::LIFT_SWITCH, NormallyOpen::Open).await?;
+//! let motor = cluster.create::<Motor, _>(Ports::CRANE_MOTOR, ()).await?;
+//! cluster.halt().await;
+//! # Ok(())
+//! # }
+//! ```
+
+use tokio::task::JoinSet;
+use crate::connection::ConnectionState;
+use crate::direct::WhoamiResponse;
+use crate::discovery::Discovery;
+use crate::error::FtSwarmError;
+use crate::swarm_object::{Io, SwarmObject};
+use crate::FtSwarm;
+
+struct Member {
+    whoami: WhoamiResponse,
+    swarm: FtSwarm,
+}
+
+/// Connections to several ftSwarms, keyed by their hostnames and serial numbers
+#[derive(Default)]
+pub struct SwarmCluster {
+    members: Vec<Member>,
+}
+
+impl SwarmCluster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect to every ftSwarm the discovery finds
+    ///
+    /// An ftSwarm that can't be added, e.g. because its hostname is taken, is logged and skipped
+    pub async fn discover(discovery: &Discovery) -> Result<Self, FtSwarmError> {
+        let mut cluster = SwarmCluster::new();
+        for swarm in discovery.connect_all().await? {
+            if let Err(err) = cluster.add(swarm).await {
+                log::warn!("Skipping an ftSwarm: {}", err);
+            }
+        }
+
+        Ok(cluster)
+    }
+
+    /// Add a connected ftSwarm, returns who it is
+    ///
+    /// Fails if it doesn't answer `whoami`, or if another member has the same hostname. Then the
+    /// connection is closed without halting the ftSwarm
+    pub async fn add(&mut self, swarm: FtSwarm) -> Result<WhoamiResponse, FtSwarmError> {
+        let whoami = match swarm.whoami().await {
+            Ok(whoami) => whoami,
+            Err(err) => {
+                swarm.close().await;
+                return Err(err);
+            }
+        };
+        if self.get(&whoami.hostname).is_some() {
+            swarm.close().await;
+            return Err(FtSwarmError::InvalidArgument(format!("The cluster already has an ftSwarm named {}", whoami.hostname)));
+        }
+
+        self.members.push(Member { whoami: whoami.clone(), swarm });
+        Ok(whoami)
+    }
+
+    /// The connection to the ftSwarm with this hostname or id (`ftSwarm100`), ignoring case
+    pub fn get(&self, controller: &str) -> Option<&FtSwarm> {
+        self.members.iter()
+            .find(|member| member.whoami.hostname.eq_ignore_ascii_case(controller) || member.whoami.id.eq_ignore_ascii_case(controller))
+            .map(|member| &member.swarm)
+    }
+
+    /// The connection to the ftSwarm with this serial number
+    pub fn by_serial(&self, serial: i32) -> Option<&FtSwarm> {
+        self.members.iter()
+            .find(|member| member.whoami.serial == Some(serial))
+            .map(|member| &member.swarm)
+    }
+
+    /// The members of the cluster, in the order they were added
+    pub fn members(&self) -> impl Iterator<Item=(&WhoamiResponse, &FtSwarm)> {
+        self.members.iter().map(|member| (&member.whoami, &member.swarm))
+    }
+
+    /// Split a qualified name like `kelda.A1` into the connection to its controller and the port name
+    pub fn resolve<'a>(&self, name: &'a str) -> Result<(&FtSwarm, &'a str), FtSwarmError> {
+        let (controller, port) = name.split_once('.')
+            .ok_or_else(|| FtSwarmError::InvalidArgument(format!("{} isn't qualified with the controller, e.g. kelda.A1", name)))?;
+        let swarm = self.get(controller)
+            .ok_or_else(|| FtSwarmError::NotFound(format!("Hostname {} in the cluster", controller)))?;
+
+        Ok((swarm, port))
+    }
+
+    /// Create and set up an object on the controller its qualified name points at
+    pub async fn create<T, Params>(&self, name: &str, params: Params) -> Result<Io<T>, FtSwarmError>
+    where
+        T: SwarmObject<Params> + 'static,
+    {
+        let (swarm, port) = self.resolve(name)?;
+        T::try_create(swarm, port, params).await
+    }
+
+    /// Halt every controller
+    pub async fn halt(&self) {
+        for member in &self.members {
+            member.swarm.halt().await;
+        }
+    }
+
+    /// Shut down the connection to every controller, see [`FtSwarm::shutdown`]
+    pub async fn shutdown(&self) {
+        for member in &self.members {
+            member.swarm.shutdown().await;
+        }
+    }
+
+    /// Ask every controller at once who it is, in the order they were added
+    pub async fn whoami(&self) -> Vec<(String, Result<WhoamiResponse, FtSwarmError>)> {
+        let mut requests = JoinSet::new();
+        for (index, member) in self.members.iter().enumerate() {
+            let swarm = member.swarm.clone();
+            requests.spawn(async move { (index, swarm.whoami().await) });
+        }
+
+        let mut answers: Vec<_> = requests.join_all().await;
+        answers.sort_by_key(|(index, _)| *index);
+        answers.into_iter()
+            .map(|(index, answer)| (self.members[index].whoami.hostname.clone(), answer))
+            .collect()
+    }
+
+    /// The state of the connection to every controller, in the order they were added
+    pub fn health(&self) -> Vec<(String, ConnectionState)> {
+        self.members.iter()
+            .map(|member| (member.whoami.hostname.clone(), *member.swarm.connection_state().borrow()))
+            .collect()
+    }
+
+    /// Whether every controller is connected
+    pub fn is_healthy(&self) -> bool {
+        self.health().iter().all(|(_, state)| *state == ConnectionState::Connected)
+    }
+}
@@ -0,0 +1,351 @@
+//! Describe a model in a TOML or YAML file instead of creating every object by hand
+//!
+//! The file says how to reach the controller and lists the objects of the model by alias.
+//! [`Model::load`] checks the file, connects, creates all objects and hands them out by alias.
+//!
+//! ```toml
+//! [controller]
+//! port = "/dev/ttyUSB0"   # or: address = "kelda.local:23", hostname = "kelda", serial = 100
+//!
+//! [objects.lift_switch]
+//! kind = "Switch"
+//! port = "A1"
+//! normally_open = false
+//!
+//! [objects.oven]
+//! kind = "Thermometer"
+//! port = "A2"
+//! hysteresis = 5
+//!
+//! [objects.gate]
+//! kind = "Servo"
+//! port = "SERVO1"
+//! offset = -10
+//! ```
+//!
+//! ```no_run
+//! use ftswarm::prelude::*;
+//!
+//! # async fn example() -> Result<(), FtSwarmError> {
+//! let model = Model::load("model.toml").await?;
+//! let switch = model.get::<Switch>("lift_switch")?;
+//! let servo = model.get::<Servo>("gate")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use serde::Deserialize;
+use ftswarm_proto::command::argument::Argument;
+use ftswarm_proto::command::rpc::RpcFunction;
+use ftswarm_serial::{AsyncSerialCommunication, TcpCommunication};
+use crate::connection::ReconnectPolicy;
+use crate::error::FtSwarmError;
+use crate::swarm_object::actor::*;
+use crate::swarm_object::analog::*;
+use crate::swarm_object::controller::Controller;
+use crate::swarm_object::digital::*;
+use crate::swarm_object::joystick::Joystick;
+use crate::swarm_object::led::Led;
+use crate::swarm_object::servo::Servo;
+use crate::swarm_object::stepper::Stepper;
+use crate::swarm_object::{Hysteresis, Io, NormallyOpen, SwarmObject};
+use crate::{lock, FtSwarm};
+
+/// Which object a port is used as, named like the type that is created for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ObjectKind {
+    Digital,
+    FrequencyMeter,
+    Counter,
+    LightBarrier,
+    ReedSwitch,
+    Switch,
+    RotaryEncoder,
+    Analog,
+    ColorSensor,
+    Ldr,
+    Thermometer,
+    Ohmmeter,
+    TrailSensor,
+    Ultrasonic,
+    Voltmeter,
+    Motor,
+    XMMotor,
+    Tractor,
+    Encoder,
+    Lamp,
+    Valve,
+    Compressor,
+    Buzzer,
+    Servo,
+    Led,
+    Stepper,
+    Joystick,
+    Controller,
+}
+
+impl ObjectKind {
+    fn takes_normally_open(self) -> bool {
+        use ObjectKind::*;
+        matches!(self, Digital | FrequencyMeter | Counter | LightBarrier | ReedSwitch | Switch)
+    }
+
+    fn takes_hysteresis(self) -> bool {
+        use ObjectKind::*;
+        matches!(self, Analog | ColorSensor | Ldr | Thermometer | Ohmmeter | TrailSensor | Ultrasonic | Voltmeter | Joystick)
+    }
+
+    fn takes_offset(self) -> bool {
+        self == ObjectKind::Servo
+    }
+}
+
+impl Display for ObjectKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// How to reach the controller, exactly one of the fields must be set
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ControllerConfig {
+    /// A serial port like `/dev/ttyUSB0`, reopened if the connection drops
+    pub port: Option<String>,
+    /// A host and port to connect to over WiFi, like `kelda.local:23`
+    pub address: Option<String>,
+    /// Probe all serial ports for the ftSwarm with this hostname
+    pub hostname: Option<String>,
+    /// Probe all serial ports for the ftSwarm with this serial number
+    pub serial: Option<i32>,
+}
+
+/// One object of the model
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObjectConfig {
+    pub kind: ObjectKind,
+    /// The port on the controller, like `A1` or `M2`
+    pub port: String,
+    /// Digital inputs only, `true` unless set
+    pub normally_open: Option<bool>,
+    /// Analog inputs and joysticks only, `0` unless set
+    pub hysteresis: Option<i32>,
+    /// Servos only
+    pub offset: Option<i32>,
+}
+
+/// The contents of a model file
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelConfig {
+    pub controller: ControllerConfig,
+    /// The objects, keyed by their aliases
+    #[serde(default)]
+    pub objects: BTreeMap<String, ObjectConfig>,
+}
+
+impl ModelConfig {
+    pub fn from_toml(text: &str) -> Result<Self, FtSwarmError> {
+        let config: ModelConfig = toml::from_str(text).map_err(|err| FtSwarmError::Config(err.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_yaml(text: &str) -> Result<Self, FtSwarmError> {
+        let config: ModelConfig = serde_yaml::from_str(text).map_err(|err| FtSwarmError::Config(err.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read a `.toml`, `.yaml` or `.yml` file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, FtSwarmError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| FtSwarmError::Config(format!("Failed to read {}: {}", path.display(), err)))?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => ModelConfig::from_toml(&text),
+            Some("yaml" | "yml") => ModelConfig::from_yaml(&text),
+            _ => Err(FtSwarmError::Config(format!("{} isn't a .toml, .yaml or .yml file", path.display()))),
+        }
+    }
+
+    /// Check that the controller is described once, that every object only has the options
+    /// of its kind, and that no port is used twice
+    pub fn validate(&self) -> Result<(), FtSwarmError> {
+        let controller = &self.controller;
+        let ways = [controller.port.is_some(), controller.address.is_some(), controller.hostname.is_some(), controller.serial.is_some()];
+        if ways.iter().filter(|&&set| set).count() != 1 {
+            return Err(FtSwarmError::Config("The controller needs exactly one of port, address, hostname or serial".to_string()));
+        }
+
+        let mut ports: HashMap<String, &str> = HashMap::new();
+        for (alias, object) in &self.objects {
+            let kind = object.kind;
+            let option = if object.normally_open.is_some() && !kind.takes_normally_open() {
+                Some("normally_open")
+            } else if object.hysteresis.is_some() && !kind.takes_hysteresis() {
+                Some("hysteresis")
+            } else if object.offset.is_some() && !kind.takes_offset() {
+                Some("offset")
+            } else {
+                None
+            };
+            if let Some(option) = option {
+                return Err(FtSwarmError::Config(format!("{} is a {}, which has no {}", alias, kind, option)));
+            }
+
+            if object.port.trim().is_empty() {
+                return Err(FtSwarmError::Config(format!("{} has no port", alias)));
+            }
+            if let Some(other) = ports.insert(object.port.to_ascii_uppercase(), alias) {
+                return Err(FtSwarmError::Config(format!("{} and {} both use port {}", other, alias, object.port)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Connect to the controller the way the file says
+    pub async fn connect(&self) -> Result<FtSwarm, FtSwarmError> {
+        let controller = &self.controller;
+        if let Some(port) = &controller.port {
+            let port = port.clone();
+            FtSwarm::connect_with_reconnect(move || {
+                let port = port.clone();
+                async move { AsyncSerialCommunication::open(&port) }
+            }, ReconnectPolicy::default()).await
+        } else if let Some(address) = &controller.address {
+            let address = address.clone();
+            FtSwarm::connect_with_reconnect(move || {
+                let address = address.clone();
+                async move { TcpCommunication::connect(address).await }
+            }, ReconnectPolicy::default()).await
+        } else if let Some(hostname) = &controller.hostname {
+            FtSwarm::connect_by_hostname(hostname).await
+        } else if let Some(serial) = controller.serial {
+            FtSwarm::connect_by_serial(serial).await
+        } else {
+            Err(FtSwarmError::Config("The controller isn't described".to_string()))
+        }
+    }
+}
+
+struct Entry {
+    kind: ObjectKind,
+    /// The `Io<T>` of the type named by `kind`
+    object: Box<dyn Any + Send + Sync>,
+}
+
+/// The objects of a model, created from a [`ModelConfig`] and looked up by alias
+pub struct Model {
+    swarm: FtSwarm,
+    objects: HashMap<String, Entry>,
+}
+
+async fn create<T, Params>(swarm: &FtSwarm, port: &str, params: Params) -> Result<Box<dyn Any + Send + Sync>, FtSwarmError>
+where
+    T: SwarmObject<Params> + 'static,
+{
+    Ok(Box::new(T::try_create(swarm, port, params).await?))
+}
+
+impl Model {
+    /// Read the file, connect to the controller and create all objects
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self, FtSwarmError> {
+        let config = ModelConfig::from_file(path)?;
+        let swarm = config.connect().await?;
+        Model::build(swarm, &config).await
+    }
+
+    /// Create the objects of `config` on an ftSwarm that is already connected, in the order of their aliases
+    pub async fn build(swarm: FtSwarm, config: &ModelConfig) -> Result<Self, FtSwarmError> {
+        config.validate()?;
+
+        let mut objects = HashMap::new();
+        for (alias, object) in &config.objects {
+            let created = Model::create(&swarm, object).await?;
+            objects.insert(alias.clone(), Entry { kind: object.kind, object: created });
+        }
+
+        Ok(Model { swarm, objects })
+    }
+
+    async fn create(swarm: &FtSwarm, object: &ObjectConfig) -> Result<Box<dyn Any + Send + Sync>, FtSwarmError> {
+        let port = object.port.as_str();
+        let normally_open = if object.normally_open.unwrap_or(true) { NormallyOpen::Open } else { NormallyOpen::Closed };
+        let hysteresis = Hysteresis(object.hysteresis.unwrap_or(0));
+
+        match object.kind {
+            ObjectKind::Digital => create::<Digital, _>(swarm, port, normally_open).await,
+            ObjectKind::FrequencyMeter => create::<FrequencyMeter, _>(swarm, port, normally_open).await,
+            ObjectKind::Counter => create::<Counter, _>(swarm, port, normally_open).await,
+            ObjectKind::LightBarrier => create::<LightBarrier, _>(swarm, port, normally_open).await,
+            ObjectKind::ReedSwitch => create::<ReedSwitch, _>(swarm, port, normally_open).await,
+            ObjectKind::Switch => create::<Switch, _>(swarm, port, normally_open).await,
+            ObjectKind::RotaryEncoder => create::<RotaryEncoder, _>(swarm, port, true).await,
+            ObjectKind::Analog => create::<Analog, _>(swarm, port, hysteresis).await,
+            ObjectKind::ColorSensor => create::<ColorSensor, _>(swarm, port, hysteresis).await,
+            ObjectKind::Ldr => create::<Ldr, _>(swarm, port, hysteresis).await,
+            ObjectKind::Thermometer => create::<Thermometer, _>(swarm, port, hysteresis).await,
+            ObjectKind::Ohmmeter => create::<Ohmmeter, _>(swarm, port, hysteresis).await,
+            ObjectKind::TrailSensor => create::<TrailSensor, _>(swarm, port, hysteresis).await,
+            ObjectKind::Ultrasonic => create::<Ultrasonic, _>(swarm, port, hysteresis).await,
+            ObjectKind::Voltmeter => create::<Voltmeter, _>(swarm, port, hysteresis).await,
+            ObjectKind::Motor => create::<Motor, _>(swarm, port, ()).await,
+            ObjectKind::XMMotor => create::<XMMotor, _>(swarm, port, ()).await,
+            ObjectKind::Tractor => create::<Tractor, _>(swarm, port, ()).await,
+            ObjectKind::Encoder => create::<Encoder, _>(swarm, port, ()).await,
+            ObjectKind::Lamp => create::<Lamp, _>(swarm, port, ()).await,
+            ObjectKind::Valve => create::<Valve, _>(swarm, port, ()).await,
+            ObjectKind::Compressor => create::<Compressor, _>(swarm, port, ()).await,
+            ObjectKind::Buzzer => create::<Buzzer, _>(swarm, port, ()).await,
+            ObjectKind::Servo => {
+                let servo = Servo::try_create(swarm, port, ()).await?;
+                if let Some(offset) = object.offset {
+                    // A setup command, so the offset is restored after a reconnect
+                    let handle = lock(&servo).await.clone();
+                    handle.run_setup_command(RpcFunction::SetOffset, vec![Argument::Int(offset as i64)]).await?;
+                }
+                Ok(Box::new(servo))
+            }
+            ObjectKind::Led => create::<Led, _>(swarm, port, ()).await,
+            ObjectKind::Stepper => create::<Stepper, _>(swarm, port, ()).await,
+            ObjectKind::Joystick => create::<Joystick, _>(swarm, port, hysteresis).await,
+            ObjectKind::Controller => create::<Controller, _>(swarm, port, ()).await,
+        }
+    }
+
+    /// The object with this alias, fails if there is none or it is of another type
+    pub fn get<T: 'static>(&self, alias: &str) -> Result<Io<T>, FtSwarmError> {
+        let entry = self.objects.get(alias)
+            .ok_or_else(|| FtSwarmError::NotFound(format!("Object {} in the model", alias)))?;
+
+        entry.object.downcast_ref::<Io<T>>()
+            .cloned()
+            .ok_or_else(|| {
+                let requested = std::any::type_name::<T>().rsplit("::").next().unwrap_or_default();
+                FtSwarmError::InvalidArgument(format!("{} is a {}, not a {}", alias, entry.kind, requested))
+            })
+    }
+
+    /// The kind of the object with this alias
+    pub fn kind(&self, alias: &str) -> Option<ObjectKind> {
+        self.objects.get(alias).map(|entry| entry.kind)
+    }
+
+    /// The aliases of all objects, in no particular order
+    pub fn aliases(&self) -> impl Iterator<Item=&str> {
+        self.objects.keys().map(String::as_str)
+    }
+
+    /// The connection the objects were created on
+    pub fn swarm(&self) -> &FtSwarm {
+        &self.swarm
+    }
+}
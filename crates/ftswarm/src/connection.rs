@@ -0,0 +1,153 @@
+use std::future::Future;
+use std::pin::Pin;
+use tokio::sync::watch;
+use tokio::time::Duration;
+use ftswarm_proto::command::direct::FtSwarmDirectCommand;
+use ftswarm_proto::command::rpc::{FtSwarmRPCCommand, RpcFunction};
+use ftswarm_proto::command::FtSwarmCommand;
+use ftswarm_proto::message_parser::S2RMessage;
+use ftswarm_proto::Serialized;
+use ftswarm_serial::{AsyncSwarmSerialPort, SerialError};
+use crate::error::FtSwarmError;
+use crate::{lock, InnerFtSwarm, Mutex, DEFAULT_TIMEOUT};
+
+/// The state of the link to the ftSwarm, see [`FtSwarm::connection_state`](crate::FtSwarm::connection_state)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Requests are sent to the ftSwarm
+    Connected,
+    /// The link dropped and is being restored. Requests fail with [`FtSwarmError::Disconnected`] until it is
+    Reconnecting {
+        /// The number of the current attempt, starting at 1
+        attempt: u32,
+    },
+    /// The link dropped and won't be restored. All requests fail
+    Lost,
+    /// The connection was shut down, see [`FtSwarm::shutdown`](crate::FtSwarm::shutdown). All requests fail
+    Closed,
+}
+
+/// How often and how fast a dropped connection is reopened
+///
+/// The delay before an attempt starts at `initial_delay` and doubles with every failed
+/// attempt, up to `max_delay`.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Give up after this many failed attempts, `None` retries forever
+    pub max_attempts: Option<u32>,
+}
+
+impl ReconnectPolicy {
+    /// The delay before the given attempt, starting at 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            max_attempts: None,
+        }
+    }
+}
+
+/// Switch the ftSwarm to CLI mode and wait until it is ready
+pub(crate) async fn start_cli<Port: AsyncSwarmSerialPort>(port: &mut Port) -> Result<(), SerialError> {
+    port.write_line(FtSwarmCommand::Direct(FtSwarmDirectCommand::StartCli).serialize()).await?;
+    port.block_until("@@@".to_string()).await
+}
+
+type Connect<Port> = Box<dyn Fn() -> Pin<Box<dyn Future<Output=Result<Port, SerialError>> + Send>> + Send + Sync>;
+
+/// Reopens the port after the link dropped and restores the setup of all swarm objects
+pub(crate) struct Reconnect<Port> {
+    connect: Connect<Port>,
+    policy: ReconnectPolicy,
+}
+
+impl<Port: AsyncSwarmSerialPort> Reconnect<Port> {
+    pub fn new<C, F>(connect: C, policy: ReconnectPolicy) -> Self
+    where
+        C: Fn() -> F + Send + Sync + 'static,
+        F: Future<Output=Result<Port, SerialError>> + Send + 'static,
+    {
+        Reconnect {
+            connect: Box::new(move || Box::pin(connect())),
+            policy,
+        }
+    }
+
+    /// Open the port and start the CLI
+    pub async fn open(&self) -> Result<Port, FtSwarmError> {
+        let mut port = (self.connect)().await?;
+        tokio::time::timeout(DEFAULT_TIMEOUT, start_cli(&mut port)).await
+            .map_err(|_| FtSwarmError::Timeout(DEFAULT_TIMEOUT))??;
+        Ok(port)
+    }
+
+    /// Retry until the connection is restored, or return `None` once the policy gives up
+    pub async fn run(&self, inner: &Mutex<InnerFtSwarm>, state: &watch::Sender<ConnectionState>) -> Option<Port> {
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            if self.policy.max_attempts.is_some_and(|max| attempt > max) {
+                return None;
+            }
+
+            state.send_replace(ConnectionState::Reconnecting { attempt });
+            tokio::time::sleep(self.policy.delay(attempt)).await;
+
+            match self.restore(inner).await {
+                Ok(port) => return Some(port),
+                Err(err) => log::warn!("Reconnect attempt {} failed: {}", attempt, err),
+            }
+        }
+    }
+
+    async fn restore(&self, inner: &Mutex<InnerFtSwarm>) -> Result<Port, FtSwarmError> {
+        let mut port = self.open().await?;
+
+        let setup = lock(inner).await.setup.clone();
+        for command in setup {
+            tokio::time::timeout(DEFAULT_TIMEOUT, replay(&mut port, inner, command)).await
+                .map_err(|_| FtSwarmError::Timeout(DEFAULT_TIMEOUT))??;
+        }
+
+        Ok(port)
+    }
+}
+
+/// Send a setup command again and wait for its response, if it has one
+///
+/// The ftSwarm might have changed while it was gone, so if it rejects the command, the
+/// error is logged and the remaining setup is still restored.
+async fn replay<Port: AsyncSwarmSerialPort>(port: &mut Port, inner: &Mutex<InnerFtSwarm>, command: FtSwarmRPCCommand) -> Result<(), SerialError> {
+    port.write_line(command.serialize()).await?;
+
+    // Subscribe commands don't return a response
+    if command.function == RpcFunction::Subscribe {
+        return Ok(());
+    }
+
+    loop {
+        let line = port.read_line().await?;
+        match S2RMessage::from(line.clone()) {
+            S2RMessage::Subscription(_) | S2RMessage::UserEvent(_) => lock(inner).await.handle_line(line),
+            S2RMessage::Log(_) | S2RMessage::StartCLI => {}
+            S2RMessage::Error(err) => {
+                log::warn!("Failed to restore {}: {}", command.serialize(), err);
+                break;
+            }
+            S2RMessage::RPCResponse(_) => break,
+        }
+    }
+
+    Ok(())
+}
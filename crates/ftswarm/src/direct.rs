@@ -1,7 +1,8 @@
 use std::fmt::{Display, Formatter};
 use std::time::Duration;
+use ftswarm_proto::error::ProtoError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct WhoamiResponse {
     pub hostname: String,
     pub id: String,
@@ -9,12 +10,12 @@ pub struct WhoamiResponse {
 }
 
 impl TryFrom<String> for  WhoamiResponse {
-    type Error = String;
+    type Error = ProtoError;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
         let mut parts = value.split('/');
-        let id = parts.next().ok_or("No ID found")?;
-        let hostname = parts.next().ok_or("No hostname found")?;
+        let id = parts.next().ok_or(ProtoError::UnexpectedResponse(format!("No ID found in {}", value)))?;
+        let hostname = parts.next().ok_or(ProtoError::UnexpectedResponse(format!("No hostname found in {}", value)))?;
 
         let serial = id.replace("ftSwarm", "").parse().ok();
 
@@ -32,10 +33,11 @@ impl Display for WhoamiResponse {
     }
 }
 
-pub fn parse_uptime(value: String) -> Result<Duration, String> {
-    let mut parts = value.split(':').last().ok_or("No uptime found")?;
-    parts = parts.split(".").nth(0).ok_or("No uptime found")?.trim();
-    let uptime: u64 = parts.parse().map_err(|_| "Failed to parse uptime")?;
+pub fn parse_uptime(value: String) -> Result<Duration, ProtoError> {
+    let no_uptime = || ProtoError::UnexpectedResponse(format!("No uptime found in {}", value));
+    let mut parts = value.split(':').next_back().ok_or_else(no_uptime)?;
+    parts = parts.split(".").next().ok_or_else(no_uptime)?.trim();
+    let uptime: u64 = parts.parse().map_err(|_| no_uptime())?;
 
     Ok(Duration::from_secs(uptime))
 }
\ No newline at end of file
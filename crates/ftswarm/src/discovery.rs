@@ -0,0 +1,172 @@
+//! Find ftSwarms on the serial ports of this computer
+//!
+//! Every port is opened and asked `whoami`, so ports of other devices are skipped instead of
+//! being mistaken for an ftSwarm.
+//!
+//! ```no_run
+//! use ftswarm::discovery::Discovery;
+//!
+//! # async fn example() -> Result<(), ftswarm::error::FtSwarmError> {
+//! // Only look at CP210x USB adapters
+//! for found in Discovery::new().with_usb_id(0x10c4, 0xea60).run().await? {
+//!     println!("{} on {}", found.whoami.hostname, found.port);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use tokio::task::JoinSet;
+use tokio::time::{Duration, Instant};
+use ftswarm_serial::{AsyncSerialCommunication, AsyncSwarmSerialPort, SerialCommunication, UsbId};
+use crate::direct::WhoamiResponse;
+use crate::error::FtSwarmError;
+use crate::FtSwarm;
+
+/// How long a port may take to start the CLI and answer `whoami` by default
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// An ftSwarm that answered on a serial port
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredSwarm {
+    pub port: String,
+    pub whoami: WhoamiResponse,
+}
+
+/// Which serial ports are probed, and for how long
+#[derive(Debug, Clone)]
+pub struct Discovery {
+    usb_ids: Vec<UsbId>,
+    timeout: Duration,
+}
+
+impl Default for Discovery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Start the CLI on `port` and ask who is on the other end
+///
+/// The connection is kept, so the port doesn't have to be opened again to use the ftSwarm.
+pub async fn probe<Port: AsyncSwarmSerialPort + 'static>(port: Port, timeout: Duration) -> Result<(FtSwarm, WhoamiResponse), FtSwarmError> {
+    let started = Instant::now();
+    let swarm = tokio::time::timeout(timeout, FtSwarm::new_async(port)).await
+        .map_err(|_| FtSwarmError::Timeout(timeout))??;
+
+    // Closed instead of dropped if it doesn't answer, so it isn't halted
+    let remaining = timeout.saturating_sub(started.elapsed());
+    match swarm.whoami_with_timeout(remaining).await {
+        Ok(whoami) => Ok((swarm, whoami)),
+        Err(err) => {
+            swarm.close().await;
+            Err(err)
+        }
+    }
+}
+
+impl Discovery {
+    /// Probe all serial ports
+    pub fn new() -> Self {
+        Discovery {
+            usb_ids: Vec::new(),
+            timeout: DEFAULT_PROBE_TIMEOUT,
+        }
+    }
+
+    /// Only probe USB serial ports with this vendor and product id. Can be called several times
+    pub fn with_usb_id(mut self, vid: u16, pid: u16) -> Self {
+        self.usb_ids.push(UsbId { vid, pid });
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Probe all serial ports that pass the filter at once
+    pub async fn run(&self) -> Result<Vec<DiscoveredSwarm>, FtSwarmError> {
+        let ports = SerialCommunication::list_ports(&self.usb_ids)?;
+        Ok(self.probe_ports(ports).await)
+    }
+
+    /// Probe the serial ports at the given paths at once, regardless of the filter
+    pub async fn probe_ports(&self, ports: impl IntoIterator<Item=String>) -> Vec<DiscoveredSwarm> {
+        let mut found = Vec::new();
+        for (port, swarm, whoami) in self.open_all(ports).await {
+            swarm.close().await;
+            found.push(DiscoveredSwarm { port, whoami });
+        }
+
+        found.sort_by(|a, b| a.port.cmp(&b.port));
+        found
+    }
+
+    /// Connect to the ftSwarm on the first port, by name, that answers `whoami`
+    pub async fn connect_first(&self) -> Result<FtSwarm, FtSwarmError> {
+        self.connect(|_| true, "ftSwarm").await
+    }
+
+    /// Connect to the ftSwarm with the given hostname, ignoring case
+    pub async fn connect_by_hostname(&self, hostname: &str) -> Result<FtSwarm, FtSwarmError> {
+        self.connect(|whoami| whoami.hostname.eq_ignore_ascii_case(hostname), &format!("ftSwarm with hostname {}", hostname)).await
+    }
+
+    /// Connect to the ftSwarm with the given serial number
+    pub async fn connect_by_serial(&self, serial: i32) -> Result<FtSwarm, FtSwarmError> {
+        self.connect(|whoami| whoami.serial == Some(serial), &format!("ftSwarm with serial number {}", serial)).await
+    }
+
+    /// Connect to every ftSwarm that is found, see [`SwarmCluster::discover`](crate::cluster::SwarmCluster::discover)
+    pub async fn connect_all(&self) -> Result<Vec<FtSwarm>, FtSwarmError> {
+        let ports = SerialCommunication::list_ports(&self.usb_ids)?;
+        let mut opened = self.open_all(ports).await;
+        opened.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(opened.into_iter().map(|(_, swarm, _)| swarm).collect())
+    }
+
+    async fn connect(&self, matches: impl Fn(&WhoamiResponse) -> bool, description: &str) -> Result<FtSwarm, FtSwarmError> {
+        let ports = SerialCommunication::list_ports(&self.usb_ids)?;
+        let mut opened = self.open_all(ports).await;
+        opened.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut connected = None;
+        for (port, swarm, whoami) in opened {
+            if connected.is_none() && matches(&whoami) {
+                log::info!("Found {} on {}", whoami, port);
+                connected = Some(swarm);
+            } else {
+                swarm.close().await;
+            }
+        }
+
+        connected.ok_or_else(|| FtSwarmError::NotFound(description.to_string()))
+    }
+
+    /// Open and probe the ports, returns the connections to the ftSwarms in no particular order
+    async fn open_all(&self, ports: impl IntoIterator<Item=String>) -> Vec<(String, FtSwarm, WhoamiResponse)> {
+        let mut probes = JoinSet::new();
+        for port in ports {
+            let timeout = self.timeout;
+            probes.spawn(async move {
+                let probed = match AsyncSerialCommunication::open(&port) {
+                    Ok(serial) => probe(serial, timeout).await,
+                    Err(err) => Err(err.into()),
+                };
+                (port, probed)
+            });
+        }
+
+        let mut opened = Vec::new();
+        while let Some(result) = probes.join_next().await {
+            match result {
+                Ok((port, Ok((swarm, whoami)))) => opened.push((port, swarm, whoami)),
+                Ok((port, Err(err))) => log::debug!("No ftSwarm on {}: {}", port, err),
+                Err(err) => log::warn!("Probing a port failed: {}", err),
+            }
+        }
+
+        opened
+    }
+}
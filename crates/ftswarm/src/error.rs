@@ -0,0 +1,95 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use ftswarm_proto::command::rpc::RpcFunction;
+use ftswarm_proto::error::{FirmwareError, ProtoError};
+use ftswarm_proto::NameOf;
+use ftswarm_serial::SerialError;
+
+/// Errors that can occur while talking to an ftSwarm
+#[derive(Debug)]
+pub enum FtSwarmError {
+    /// The ftSwarm didn't answer within the given time
+    Timeout(Duration),
+    /// The connection to the ftSwarm was closed before a response arrived
+    Disconnected,
+    /// A response was lost, so the pending requests were failed to keep the later responses
+    /// from being handed to the wrong request
+    OutOfSync,
+    /// The ftSwarm answered with an error message (`^ Error: ...`)
+    Firmware(FirmwareError),
+    /// A message couldn't be parsed, or the response wasn't what was expected
+    Protocol(ProtoError),
+    /// The underlying serial port failed
+    Transport(SerialError),
+    /// A swarm object couldn't be set up, `step` is the command that failed
+    Setup {
+        object: String,
+        step: RpcFunction,
+        source: Box<FtSwarmError>,
+    },
+    /// An argument is out of the range the ftSwarm accepts
+    InvalidArgument(String),
+    /// A stepper didn't hit the end switch in time while homing
+    HomingFailed {
+        object: String,
+    },
+    /// What was looked up doesn't exist, like an ftSwarm during [`discovery`](crate::discovery) or an object of a model
+    NotFound(String),
+    /// A model description couldn't be read or doesn't make sense, see [`config`](crate::config)
+    Config(String),
+}
+
+impl FtSwarmError {
+    /// Shorthand for a response that doesn't have the expected type
+    pub(crate) fn unexpected(message: &str) -> Self {
+        FtSwarmError::Protocol(ProtoError::UnexpectedResponse(message.to_string()))
+    }
+}
+
+impl Display for FtSwarmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FtSwarmError::Timeout(timeout) => write!(f, "No response from the ftSwarm within {:?}", timeout),
+            FtSwarmError::Disconnected => write!(f, "Connection to the ftSwarm was closed"),
+            FtSwarmError::OutOfSync => write!(f, "A response of the ftSwarm was lost, the request was failed to stay in step"),
+            FtSwarmError::Firmware(err) => write!(f, "ftSwarm error: {}", err),
+            FtSwarmError::Protocol(err) => write!(f, "Protocol error: {}", err),
+            FtSwarmError::Transport(err) => write!(f, "Transport error: {}", err),
+            FtSwarmError::Setup { object, step, source } => write!(f, "Failed to set up {}, {} failed: {}", object, step.name(), source),
+            FtSwarmError::InvalidArgument(message) => write!(f, "Invalid argument: {}", message),
+            FtSwarmError::HomingFailed { object } => write!(f, "Homing {} failed, the end switch wasn't reached", object),
+            FtSwarmError::NotFound(description) => write!(f, "{} not found", description),
+            FtSwarmError::Config(message) => write!(f, "Invalid configuration: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for FtSwarmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FtSwarmError::Firmware(err) => Some(err),
+            FtSwarmError::Protocol(err) => Some(err),
+            FtSwarmError::Transport(err) => Some(err),
+            FtSwarmError::Setup { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<FirmwareError> for FtSwarmError {
+    fn from(value: FirmwareError) -> Self {
+        FtSwarmError::Firmware(value)
+    }
+}
+
+impl From<ProtoError> for FtSwarmError {
+    fn from(value: ProtoError) -> Self {
+        FtSwarmError::Protocol(value)
+    }
+}
+
+impl From<SerialError> for FtSwarmError {
+    fn from(value: SerialError) -> Self {
+        FtSwarmError::Transport(value)
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
 
 #[cfg(not(feature = "tokio_mutex"))]
@@ -8,25 +9,41 @@ use std::sync::Mutex as StdMutex;
 use tokio::sync::Mutex as TokioMutex;
 
 use proto::message_parser::subscription::Subscription;
+use tokio::runtime::RuntimeFlavor;
 use tokio::task::JoinHandle;
-use tokio::time::{Duration, sleep};
+use tokio::time::{Duration, Instant};
 use ftswarm_proto::command::direct::FtSwarmDirectCommand;
 use ftswarm_proto::command::FtSwarmCommand;
 use ftswarm_proto::message_parser::rpc::RPCReturnParam;
 use ftswarm_proto::message_parser::S2RMessage;
+use ftswarm_proto::message_parser::user_event::UserEvent;
 use ftswarm_proto::Serialized;
-use ftswarm_serial::SwarmSerialPort;
+use ftswarm_serial::{AsyncSerialCommunication, AsyncSwarmSerialPort, BlockingAdapter, SerialError, SwarmSerialPort};
 use ftswarm_serial::serial::SerialCommunication;
-use crate::message_queue::{ReturnQueue, SenderHandle, WriteQueue};
+use tokio::sync::{broadcast, oneshot, watch, Notify};
+use crate::message_queue::{Reply, Responder, ReturnQueue, WriteQueue};
 
 pub use ftswarm_proto as proto;
-use ftswarm_proto::command::rpc::RpcFunction;
-use crate::direct::{parse_uptime, WhoamiResponse};
+use ftswarm_proto::command::argument::Argument;
+use ftswarm_proto::command::rpc::{FtSwarmRPCCommand, RpcFunction};
+use crate::connection::{start_cli, ConnectionState, Reconnect, ReconnectPolicy};
+use crate::direct::parse_uptime;
+use crate::discovery::Discovery;
+use crate::shutdown::{Lifecycle, Watchdog};
+pub use crate::direct::WhoamiResponse;
+use crate::error::FtSwarmError;
 
 mod message_queue;
+pub mod error;
+pub mod cluster;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod connection;
+pub mod discovery;
 pub mod swarm_object;
 mod direct;
 pub mod prelude;
+pub mod shutdown;
 
 #[cfg(test)]
 mod tests;
@@ -38,12 +55,14 @@ pub type Mutex<T> = TokioMutex<T>;
 pub type Mutex<T> = StdMutex<T>;
 
 #[cfg(feature = "tokio_mutex")]
-async fn lock<T>(mutex: &Mutex<T>) -> tokio::sync::MutexGuard<T> {
+/// Locks a [`Mutex`], whichever mutex type is selected.
+pub async fn lock<T>(mutex: &Mutex<T>) -> tokio::sync::MutexGuard<'_, T> {
     mutex.lock().await
 }
 
 #[cfg(not(feature = "tokio_mutex"))]
-async fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<T> {
+/// Locks a [`Mutex`], whichever mutex type is selected.
+pub async fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
     mutex.lock().unwrap()
 }
 
@@ -91,6 +110,10 @@ struct InnerFtSwarm {
     objects: HashMap<String, Box<dyn Fn(RPCReturnParam) + Send>>,
     message_queue: ReturnQueue,
     write_queue: WriteQueue,
+    /// Setup commands of the registered objects, replayed after a reconnect
+    setup: Vec<FtSwarmRPCCommand>,
+    user_events: broadcast::Sender<UserEvent>,
+    disconnected: bool,
 }
 
 impl InnerFtSwarm {
@@ -99,66 +122,360 @@ impl InnerFtSwarm {
             objects: HashMap::new(),
             message_queue: ReturnQueue::new(),
             write_queue: WriteQueue::new(),
+            setup: Vec::new(),
+            user_events: broadcast::Sender::new(USER_EVENT_CAPACITY),
+            disconnected: false,
+        }
+    }
+
+    fn handle_line(&mut self, line: String) {
+        match S2RMessage::from(line) {
+            S2RMessage::Subscription(subscription) => {
+                if let Ok(subscription) = Subscription::try_from(subscription) {
+                    if let Some(object) = self.objects.get(&subscription.port_name) {
+                        object(subscription.value.clone());
+                    }
+                }
+            }
+            S2RMessage::UserEvent(event) => match UserEvent::try_from(event) {
+                // Nobody listening isn't an error
+                Ok(event) => { let _ = self.user_events.send(event); }
+                Err(err) => log::warn!("Ignoring user event: {}", err),
+            },
+            response => self.message_queue.push(response),
         }
     }
 }
 
+/// How many user events are kept for a receiver that falls behind
+const USER_EVENT_CAPACITY: usize = 64;
+
+/// How long to wait for a response if no other timeout was set
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// A struct representing a connection to an ftSwarm
 pub struct FtSwarm {
     inner: Arc<Mutex<InnerFtSwarm>>,
+    wake: Arc<Notify>,
     coro: Option<JoinHandle<()>>,
+    timeout: Duration,
+    state: watch::Receiver<ConnectionState>,
+    user_events: broadcast::Sender<UserEvent>,
+    lifecycle: Arc<Lifecycle>,
+    /// Tells the I/O task to halt the ftSwarm and stop
+    stop: Arc<watch::Sender<bool>>,
 }
 
 impl FtSwarm {
     /// Create a new FtSwarm instance, you must provide a serial port to connect to it
-    pub fn new<Serial: SwarmSerialPort + 'static>(mut serial: Serial) -> Self {
-        let inner = Arc::new(Mutex::new(InnerFtSwarm::new()));
+    pub fn new<Serial: SwarmSerialPort + 'static>(serial: Serial) -> Self {
+        FtSwarm::try_new(serial).expect("Failed to start the ftSwarm CLI")
+    }
 
-        let inner_for_thread = inner.clone();
+    /// Like [`FtSwarm::new`], but returns an error if the CLI can't be started
+    ///
+    /// The blocking port is moved to its own thread, see [`BlockingAdapter`]
+    pub fn try_new<Serial: SwarmSerialPort + 'static>(mut serial: Serial) -> Result<Self, FtSwarmError> {
         // Startup swarm serial mode
+        serial.write_line(FtSwarmCommand::Direct(FtSwarmDirectCommand::StartCli).serialize())?;
+        serial.block_until("@@@".to_string())?;
+
+        Ok(FtSwarm::spawn(BlockingAdapter::new(serial), None))
+    }
+
+    /// Connect to an ftSwarm through an async port, e.g. [`AsyncSerialCommunication`]
+    ///
+    /// If the connection drops, it isn't restored, see [`FtSwarm::connect_with_reconnect`]
+    pub async fn new_async<Port: AsyncSwarmSerialPort + 'static>(mut port: Port) -> Result<Self, FtSwarmError> {
+        start_cli(&mut port).await?;
+
+        Ok(FtSwarm::spawn(port, None))
+    }
+
+    /// Connect to an ftSwarm, and reconnect whenever the connection drops
+    ///
+    /// `connect` is called to open the port, and again for every reconnect attempt. After a
+    /// reconnect, the setup of all swarm objects is sent again. Use
+    /// [`FtSwarm::connection_state`] to follow the state of the connection.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ftswarm::prelude::*;
+    ///
+    /// # async fn example() -> Result<(), FtSwarmError> {
+    /// let swarm = FtSwarm::connect_with_reconnect(
+    ///     || async { AsyncSerialCommunication::open("/dev/ttyUSB0") },
+    ///     ReconnectPolicy::default(),
+    /// ).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn connect_with_reconnect<Port, Connect, Fut>(connect: Connect, policy: ReconnectPolicy) -> Result<Self, FtSwarmError>
+    where
+        Port: AsyncSwarmSerialPort + 'static,
+        Connect: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output=Result<Port, SerialError>> + Send + 'static,
+    {
+        let reconnect = Reconnect::new(connect, policy);
+        let port = reconnect.open().await?;
 
-        serial.write_line(FtSwarmCommand::Direct(FtSwarmDirectCommand::StartCli).serialize()).expect("Write line failure");
-        serial.block_until("@@@".to_string()).expect("Block until failure");
+        Ok(FtSwarm::spawn(port, Some(reconnect)))
+    }
+
+    /// Open the serial port at `tty`, and reopen it whenever the connection drops
+    pub async fn open_reconnecting(tty: &str, policy: ReconnectPolicy) -> Result<Self, FtSwarmError> {
+        let tty = tty.to_string();
+        FtSwarm::connect_with_reconnect(move || {
+            let tty = tty.clone();
+            async move { AsyncSerialCommunication::open(&tty) }
+        }, policy).await
+    }
+
+    /// Probe all serial ports and connect to the first ftSwarm that answers, see [`Discovery`]
+    pub async fn connect_first() -> Result<Self, FtSwarmError> {
+        Discovery::new().connect_first().await
+    }
+
+    /// Probe all serial ports and connect to the ftSwarm with the given hostname, see [`Discovery`]
+    pub async fn connect_by_hostname(hostname: &str) -> Result<Self, FtSwarmError> {
+        Discovery::new().connect_by_hostname(hostname).await
+    }
+
+    /// Probe all serial ports and connect to the ftSwarm with the given serial number, see [`Discovery`]
+    pub async fn connect_by_serial(serial: i32) -> Result<Self, FtSwarmError> {
+        Discovery::new().connect_by_serial(serial).await
+    }
+
+    /// Stop talking to the ftSwarm and wait until the port is closed
+    pub(crate) async fn close(mut self) {
+        if let Some(coro) = self.coro.take() {
+            coro.abort();
+            let _ = coro.await;
+        }
+    }
+
+    fn spawn<Port: AsyncSwarmSerialPort + 'static>(port: Port, reconnect: Option<Reconnect<Port>>) -> Self {
+        let inner = InnerFtSwarm::new();
+        let user_events = inner.user_events.clone();
+        let inner = Arc::new(Mutex::new(inner));
+        let wake = Arc::new(Notify::new());
+        let (state_sender, state) = watch::channel(ConnectionState::Connected);
+        let (stop, mut stopped) = watch::channel(false);
+
+        let lifecycle = Arc::new(Lifecycle::new());
+        lifecycle.set_writer(port.blocking_writer());
+
+        let inner_for_thread = inner.clone();
+        let wake_for_thread = wake.clone();
+        let lifecycle_for_thread = lifecycle.clone();
 
         let handle = tokio::spawn(async move {
-            FtSwarm::input_loop(inner_for_thread, serial).await;
+            let mut port = port;
+
+            loop {
+                if let Err(err) = FtSwarm::input_loop(inner_for_thread.clone(), wake_for_thread.clone(), port, &mut stopped).await {
+                    log::error!("Lost connection to the ftSwarm: {}", err);
+                }
+
+                {
+                    // Dropping the responders fails all pending requests
+                    let mut inner = lock(&inner_for_thread).await;
+                    inner.message_queue = ReturnQueue::new();
+                    inner.write_queue = WriteQueue::new();
+                    inner.disconnected = true;
+                }
+
+                let restored = match &reconnect {
+                    _ if *stopped.borrow() => None,
+                    Some(reconnect) => tokio::select! {
+                        restored = reconnect.run(&inner_for_thread, &state_sender) => restored,
+                        _ = stopped.wait_for(|stopped| *stopped) => None,
+                    },
+                    None => None,
+                };
+
+                match restored {
+                    Some(restored) => {
+                        lifecycle_for_thread.set_writer(restored.blocking_writer());
+                        port = restored;
+                    }
+                    None if *stopped.borrow() => {
+                        state_sender.send_replace(ConnectionState::Closed);
+                        return;
+                    }
+                    None => {
+                        state_sender.send_replace(ConnectionState::Lost);
+                        return;
+                    }
+                }
+
+                lock(&inner_for_thread).await.disconnected = false;
+                state_sender.send_replace(ConnectionState::Connected);
+                log::info!("Reconnected to the ftSwarm");
+            }
         });
 
         FtSwarm {
             inner,
+            wake,
             coro: Some(handle),
+            timeout: DEFAULT_TIMEOUT,
+            state,
+            user_events,
+            lifecycle,
+            stop: Arc::new(stop),
         }
     }
 
-    async fn input_loop<Serial: SwarmSerialPort + 'static>(inner_ft_swarm: Arc<Mutex<InnerFtSwarm>>, mut serial_port: Serial) {
-        loop {
-            if serial_port.available().expect("Available check failure") {
-                let line = serial_port.read_line().expect("Readline failure").replace("\n", "").replace("\r", "");
-                let response = S2RMessage::from(line);
-                {
-                    let mut inner = lock(&inner_ft_swarm).await;
-                    if let S2RMessage::Subscription(subscription) = response {
-                        if let Ok(subscription) = Subscription::try_from(subscription) {
-                            if let Some(object) = inner.objects.get(&subscription.port_name) {
-                                object(subscription.value.clone());
-                            }
-                        }
-                    } else {
-                        inner.message_queue.push(response);
-                    }
-                }
+    /// Run `action` when the connection is shut down, before the ftSwarm is halted
+    ///
+    /// The actions run in the order they were registered and share a deadline, see
+    /// [`FtSwarm::with_shutdown_deadline`]. Use them to e.g. park a servo or turn an RGB LED red,
+    /// which `halt` leaves on.
+    pub fn on_shutdown<Action, Fut>(&self, action: Action)
+    where
+        Action: FnOnce(FtSwarm) -> Fut + Send + 'static,
+        Fut: Future<Output=()> + Send + 'static,
+    {
+        self.lifecycle.push_action(Box::new(move |swarm| Box::pin(action(swarm))));
+    }
+
+    /// Set how long the shutdown actions may take together, for this handle and its clones.
+    /// The ftSwarm is halted when the deadline passes, even if they didn't finish
+    pub fn with_shutdown_deadline(self, deadline: Duration) -> Self {
+        self.lifecycle.set_deadline(deadline);
+        self
+    }
+
+    /// Run the shutdown actions, halt the ftSwarm and close the connection
+    ///
+    /// This happens when the handle that opened the connection is dropped as well, see
+    /// [`shutdown`](crate::shutdown). Afterwards, all requests fail with
+    /// [`FtSwarmError::Disconnected`].
+    pub async fn shutdown(&self) {
+        if !*self.stop.borrow() {
+            if !self.lifecycle.run_actions(self).await {
+                log::warn!("Shutdown actions didn't finish before the deadline");
+            }
+            self.stop.send_replace(true);
+        }
+
+        // Wait until the halt was written
+        let mut state = self.state.clone();
+        let closed = state.wait_for(|state| matches!(state, ConnectionState::Closed | ConnectionState::Lost));
+        let _ = tokio::time::timeout(self.timeout, closed).await;
+    }
+
+    /// Shut down when Ctrl-C is pressed
+    ///
+    /// The returned task finishes once the ftSwarm was halted, so the program can decide what
+    /// to do next, e.g. leave its control loop or exit.
+    pub fn shutdown_on_ctrl_c(&self) -> JoinHandle<()> {
+        let swarm = self.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                log::info!("Ctrl-C pressed, shutting down");
+                swarm.shutdown().await;
             }
+        })
+    }
 
+    /// Halt the ftSwarm whenever no command was sent through this handle or its clones for `timeout`
+    pub fn watchdog(&self, timeout: Duration) -> Watchdog {
+        Watchdog::start(self.clone(), self.lifecycle.clone(), timeout)
+    }
+
+    /// Queue a `halt` that doesn't count as activity for the watchdog
+    pub(crate) async fn queue_halt(&self) {
+        let mut inner = lock(&self.inner).await;
+        inner.write_queue.push(FtSwarmCommand::Direct(FtSwarmDirectCommand::Halt));
+        self.wake.notify_one();
+    }
+
+    /// Receive the user events raised by code running on the ftSwarm
+    ///
+    /// Every receiver gets every event sent after it was created. A receiver that falls
+    /// more than 64 events behind skips the oldest ones, see [`broadcast::Receiver::recv`]
+    ///
+    /// **Experimental:** the `U: ` line the events are parsed from isn't documented by the
+    /// firmware, so events may never arrive.
+    pub fn user_events(&self) -> broadcast::Receiver<UserEvent> {
+        self.user_events.subscribe()
+    }
+
+    /// Follow the state of the connection
+    ///
+    /// The receiver can be turned into a `Stream` with `tokio_stream::wrappers::WatchStream`
+    pub fn connection_state(&self) -> watch::Receiver<ConnectionState> {
+        self.state.clone()
+    }
+
+    /// Set the default timeout for requests made through this handle and its clones
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The default timeout for requests made through this handle
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sleeps until a line arrives or a command is queued, then handles everything that is pending
+    async fn input_loop<Port: AsyncSwarmSerialPort>(
+        inner_ft_swarm: Arc<Mutex<InnerFtSwarm>>,
+        wake: Arc<Notify>,
+        mut port: Port,
+        stop: &mut watch::Receiver<bool>,
+    ) -> Result<(), SerialError> {
+        loop {
+            // A dropped sender means all handles are gone, which stops the loop as well
+            let deadline = lock(&inner_ft_swarm).await.message_queue.deadline();
+            let (line, stopping) = tokio::select! {
+                line = port.read_line() => (Some(line?), false),
+                _ = wake.notified() => (None, false),
+                _ = stop.wait_for(|stop| *stop) => (None, true),
+                _ = sleep_until_some(deadline) => (None, false),
+            };
+
+            let mut writes = Vec::new();
             {
                 let mut inner = lock(&inner_ft_swarm).await;
+                let now = Instant::now();
+                inner.message_queue.check_lost(now);
+
+                if let Some(line) = line {
+                    inner.handle_line(line);
+                }
 
-                // Handle outputs
-                if let Some(data) = inner.write_queue.pop() {
-                    serial_port.write_line(data).expect("Write line failure");
+                if inner.message_queue.is_marker_overdue(now) {
+                    return Err(SerialError::Timeout);
+                }
+                writes.extend(inner.message_queue.take_marker(now));
+
+                // Handle outputs, the responder is registered before its command is written
+                while !inner.message_queue.is_busy() {
+                    let Some((data, responder)) = inner.write_queue.pop() else { break };
+                    match (data, responder) {
+                        (Some(data), responder) => {
+                            inner.message_queue.written(&data, responder, now);
+                            writes.push(data);
+                        }
+                        (None, Some(responder)) => inner.message_queue.push_responder(responder),
+                        (None, None) => {}
+                    }
                 }
             }
 
-            sleep(Duration::from_millis(15)).await;
+            for data in writes {
+                port.write_line(data).await?;
+            }
+
+            if stopping {
+                port.write_line(FtSwarmCommand::Direct(FtSwarmDirectCommand::Halt).serialize()).await?;
+                return Ok(());
+            }
         }
     }
 
@@ -168,97 +485,229 @@ pub(crate) async fn push_cache(&self, object: Box<dyn Fn(RPCReturnParam) + Send>
     inner.objects.insert(name.to_string(), object);
 }
 
+/// Remember a setup command so it can be sent again after a reconnect. It replaces an
+/// earlier call of the same function on the same object
+pub(crate) async fn record_setup(&self, command: FtSwarmRPCCommand) {
+    let mut inner = lock(&self.inner).await;
+    inner.setup.retain(|recorded| !replaces(&command, recorded));
+    inner.setup.push(command);
+}
+
+/// The recorded setup of the object `name`
+pub(crate) async fn setup_of(&self, name: &str) -> Vec<FtSwarmRPCCommand> {
+    let inner = lock(&self.inner).await;
+    inner.setup.iter().filter(|recorded| recorded.target == name).cloned().collect()
+}
+
+/// Put back the setup `name` had before an object by that name failed to be created
+pub(crate) async fn restore_setup(&self, name: &str, previous: Vec<FtSwarmRPCCommand>) {
+    let mut inner = lock(&self.inner).await;
+    inner.setup.retain(|recorded| recorded.target != name);
+    inner.setup.extend(previous);
+}
+
 /// Low-level method to send a command to the ftSwarm. Only use this as a last resort
+#[deprecated(note = "use `transact`, which returns the response to its own command")]
 pub async fn send_command(&self, command: FtSwarmCommand) {
+    self.queue_command(command).await
+}
+
+/// Queue a command whose response, if it has one, nobody waits for
+pub(crate) async fn queue_command(&self, command: FtSwarmCommand) {
+    self.lifecycle.touch();
     let mut inner = lock(&self.inner).await;
     inner.write_queue.push(command);
+    self.wake.notify_one();
 }
 
 /// Low-level method to receive a response to the ftSwarm. Only use this as a last resort
-pub async fn read_response(&self) -> Result<RPCReturnParam, String> {
-    let (handle, mut recv) = SenderHandle::create();
+///
+/// This receives the response to the next command sent after all commands that are already queued,
+/// not necessarily the one just sent with [`FtSwarm::send_command`]
+#[deprecated(note = "use `transact`, which returns the response to its own command")]
+pub async fn read_response(&self) -> Result<RPCReturnParam, FtSwarmError> {
+    self.lifecycle.touch();
+    let (responder, recv) = Responder::create(self.timeout);
     {
         let mut inner = lock(&self.inner).await;
-        inner.message_queue.push_sender(&handle);
+        if inner.disconnected {
+            return Err(FtSwarmError::Disconnected);
+        }
+        inner.write_queue.push_responder(responder);
+        self.wake.notify_one();
     }
 
-    let response = recv.recv().await.unwrap();
+    Self::await_response(recv, self.timeout).await
+}
 
-    {
-        let mut inner = lock(&self.inner).await;
-        inner.message_queue.drop_sender(&handle);
-    }
+async fn await_response(recv: oneshot::Receiver<Reply>, timeout: Duration) -> Result<RPCReturnParam, FtSwarmError> {
+    let response = tokio::time::timeout(timeout, recv).await
+        .map_err(|_| FtSwarmError::Timeout(timeout))?
+        .map_err(|_| FtSwarmError::Disconnected)?
+        .map_err(|_| FtSwarmError::OutOfSync)?;
 
     match response {
         S2RMessage::RPCResponse(data) => Ok(RPCReturnParam::from(data)),
-        S2RMessage::Error(data) => Err(data),
-        any => Err(format!("Received non-RPCResponse message, {:?}", any).to_string()),
+        S2RMessage::Error(error) => Err(FtSwarmError::Firmware(error)),
+        any => Err(FtSwarmError::unexpected(&format!("Received non-RPCResponse message, {:?}", any))),
     }
 }
 
 
 /// Low-level method to send a command to the ftSwarm and receive a response. Only use this as a last resort
-pub async fn transact(&self, command: FtSwarmCommand) -> Result<RPCReturnParam, String> {
+///
+/// Commands are written in the order they are submitted, and each call receives exactly the
+/// response to its own command, so this is safe to use from several tasks at once
+pub async fn transact(&self, command: FtSwarmCommand) -> Result<RPCReturnParam, FtSwarmError> {
+    self.transact_with_timeout(command, self.timeout).await
+}
+
+/// Like [`FtSwarm::transact`], but with a timeout for this call only
+///
+/// If the call times out or is cancelled, a response that arrives later is discarded
+pub async fn transact_with_timeout(&self, command: FtSwarmCommand, timeout: Duration) -> Result<RPCReturnParam, FtSwarmError> {
     // Subscribe commands don't return a response
     let is_subscription = match &command {
         FtSwarmCommand::RPC(cmd) => cmd.function == RpcFunction::Subscribe,
         _ => false,
     };
 
-    self.send_command(command).await;
-
     if is_subscription {
+        self.queue_command(command).await;
         return Ok(RPCReturnParam::Ok);
     }
 
-    self.read_response().await
+    self.lifecycle.touch();
+    let (responder, recv) = Responder::create(timeout);
+    {
+        let mut inner = lock(&self.inner).await;
+        if inner.disconnected {
+            return Err(FtSwarmError::Disconnected);
+        }
+        inner.write_queue.push_request(command, responder);
+        self.wake.notify_one();
+    }
+
+    Self::await_response(recv, timeout).await
 }
 
 /// Return the hostname, id, and serial number of the connected ftSwarm
-pub async fn whoami(&self) -> Result<WhoamiResponse, String> {
-    let response = self.transact(FtSwarmCommand::Direct(FtSwarmDirectCommand::Whoami)).await?;
+pub async fn whoami(&self) -> Result<WhoamiResponse, FtSwarmError> {
+    self.whoami_with_timeout(self.timeout).await
+}
+
+pub(crate) async fn whoami_with_timeout(&self, timeout: Duration) -> Result<WhoamiResponse, FtSwarmError> {
+    let response = self.transact_with_timeout(FtSwarmCommand::Direct(FtSwarmDirectCommand::Whoami), timeout).await?;
     if let RPCReturnParam::String(str) = response {
         Ok(WhoamiResponse::try_from(str)?)
     } else {
-        Err("Received non-string response".to_string())
+        Err(FtSwarmError::unexpected("Received non-string response"))
     }
 }
 
 /// Stop all connected motors and turn off all LEDs (except for RGB LEDs)
 pub async fn halt(&self) {
-    self.send_command(FtSwarmCommand::Direct(FtSwarmDirectCommand::Halt)).await;
+    self.queue_command(FtSwarmCommand::Direct(FtSwarmDirectCommand::Halt)).await;
 }
 
 /// Return the uptime of the connected ftSwarm (max precision: seconds)
-pub async fn uptime(&self) -> Result<Duration, String> {
+pub async fn uptime(&self) -> Result<Duration, FtSwarmError> {
     let response = self.transact(FtSwarmCommand::Direct(FtSwarmDirectCommand::Uptime)).await?;
 
     if let RPCReturnParam::String(str) = response {
         Ok(parse_uptime(str)?)
     } else {
-        Err("Received non-string response".to_string())
+        Err(FtSwarmError::unexpected("Received non-string response"))
     }
 }
 }
 
+/// Sleep until `deadline`, or forever without one
+async fn sleep_until_some(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Whether a setup command overrides an earlier one. Triggers are set per event, so
+/// different events of the same input don't replace each other
+fn replaces(command: &FtSwarmRPCCommand, earlier: &FtSwarmRPCCommand) -> bool {
+    if command.target != earlier.target || command.function != earlier.function {
+        return false;
+    }
+
+    if command.function.is_trigger() {
+        return command.args.first().map(Argument::serialize) == earlier.args.first().map(Argument::serialize);
+    }
+
+    true
+}
+
+/// Dropping the handle that opened the connection shuts it down before `drop` returns, see
+/// [`shutdown`](crate::shutdown)
 impl Drop for FtSwarm {
     fn drop(&mut self) {
-        if let Some(coro) = self.coro.take() {
-            coro.abort();
+        let Some(coro) = self.coro.take() else { return };
+        if coro.is_finished() {
+            return;
+        }
+
+        match tokio::runtime::Handle::try_current() {
+            // Other threads run the shutdown while this one waits for it
+            Ok(runtime) if runtime.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                let (done, finished) = std::sync::mpsc::channel();
+                let swarm = self.clone();
+                runtime.spawn(async move {
+                    swarm.shutdown().await;
+                    let _ = done.send(());
+                });
+
+                // Fails at once if the runtime is shutting down and drops the task
+                let deadline = self.lifecycle.deadline() + self.timeout;
+                if tokio::task::block_in_place(|| finished.recv_timeout(deadline)).is_err() {
+                    self.lifecycle.halt_blocking();
+                }
+            }
+            runtime => {
+                if !self.lifecycle.halt_blocking() {
+                    // The port needs the runtime, so halting is up to the runtime running the task
+                    if let Ok(runtime) = runtime {
+                        let swarm = self.clone();
+                        runtime.spawn(async move { swarm.shutdown().await });
+                        return;
+                    }
+                }
+                self.stop.send_replace(true);
+            }
         }
+
+        coro.abort();
     }
 }
 
 impl Clone for FtSwarm {
     fn clone(&self) -> Self {
-        Self { inner: self.inner.clone(), coro: None }
+        Self {
+            inner: self.inner.clone(),
+            wake: self.wake.clone(),
+            coro: None,
+            timeout: self.timeout,
+            state: self.state.clone(),
+            user_events: self.user_events.clone(),
+            lifecycle: self.lifecycle.clone(),
+            stop: self.stop.clone(),
+        }
     }
 }
 
-/// You can use the default implementation of FtSwarm if you just want to connect to the
-/// first available ftSwarm
+/// Deprecated: opens the first serial port without checking that an ftSwarm is connected to it,
+/// and panics if that fails. Use [`FtSwarm::connect_first`] instead
 impl Default for FtSwarm {
     fn default() -> Self {
-        FtSwarm::new(SerialCommunication::default())
+        log::warn!("FtSwarm::default is deprecated, use FtSwarm::connect_first");
+        let serial = SerialCommunication::open_first_available()
+            .unwrap_or_else(|err| panic!("Failed to open the first serial port: {}", err));
+        FtSwarm::new(serial)
     }
 }
\ No newline at end of file
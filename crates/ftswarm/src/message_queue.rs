@@ -1,41 +1,104 @@
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::collections::VecDeque;
+use tokio::sync::oneshot;
+use tokio::time::{Duration, Instant};
+use ftswarm_proto::command::direct::FtSwarmDirectCommand;
 use ftswarm_proto::command::FtSwarmCommand;
 use ftswarm_proto::message_parser::S2RMessage;
 use ftswarm_proto::Serialized;
+use crate::direct::WhoamiResponse;
 
-type Id = i128;
+/// What a request gets back: its response, or `OutOfSync` if it was failed because a
+/// response was lost
+pub type Reply = Result<S2RMessage, OutOfSync>;
 
-pub struct ReturnQueue {
-    queue: Vec<S2RMessage>,
-    senders: HashMap<Id, Arc<mpsc::Sender<S2RMessage>>>,
-}
+/// A response was lost, so it's unknown which of the later responses belongs to which request
+#[derive(Debug)]
+pub struct OutOfSync;
+
+/// How many answered commands may be written before the first of them is answered
+const MAX_IN_FLIGHT: usize = 8;
+
+/// How long the marker sent to get back in step may take to be answered
+const MARKER_TIMEOUT: Duration = Duration::from_secs(2);
 
-pub struct SenderHandle {
-    pub receiver: mpsc::Sender<S2RMessage>,
-    uid: Id,
+/// The sending half of a single pending request.
+///
+/// When the caller gives up (timeout or cancellation), the responder stays in place, so a late
+/// reply is still consumed by it instead of being handed to the next caller.
+pub struct Responder {
+    sender: oneshot::Sender<Reply>,
+    timeout: Duration,
+    /// When the reply is considered lost, twice the timeout after the command was written
+    expires: Option<Instant>,
+    /// Whether the reply could be taken for the answer to the marker
+    like_marker: bool,
 }
 
-impl SenderHandle {
-    pub fn new(receiver: mpsc::Sender<S2RMessage>) -> Self {
-        SenderHandle {
-            receiver,
-            uid: rand::random::<Id>() << 64 | std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_micros() as Id,
-        }
+impl Responder {
+    pub fn create(timeout: Duration) -> (Self, oneshot::Receiver<Reply>) {
+        let (sender, receiver) = oneshot::channel();
+        let responder = Responder {
+            sender,
+            timeout,
+            expires: None,
+            like_marker: false,
+        };
+        (responder, receiver)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.sender.is_closed()
     }
 
-    pub fn create() -> (Self, mpsc::Receiver<S2RMessage>) {
-        let (s, r) = mpsc::channel(1);
-        (SenderHandle::new(s), r)
+    fn is_written(&self) -> bool {
+        self.expires.is_some()
     }
+
+    fn send(self, value: Reply) {
+        // If the caller has gone away, the response still belongs to it and is dropped here
+        let _ = self.sender.send(value);
+    }
+}
+
+/// Getting back in step after a reply was lost
+///
+/// The ftSwarm answers in order, so the replies to the failed requests arrive before the answer
+/// to the marker. Those that look like it, the answers to a `whoami` of a caller, are skipped.
+struct Resync {
+    /// When the marker was written
+    written: Option<Instant>,
+    /// How many replies that look like the answer to the marker are still to be skipped
+    skip: usize,
+    /// Whether such a reply was skipped after the marker was written
+    skipped_since_written: bool,
+}
+
+/// Hands every response to the request it belongs to.
+///
+/// The ftSwarm answers commands strictly in the order they were written, so the
+/// responders are kept in the same order. Responses that arrive while nobody is waiting
+/// are dropped, they can't belong to a later request.
+///
+/// Up to `MAX_IN_FLIGHT` answered commands are written ahead of their replies. Once a request
+/// timed out, no further command is written until its reply arrives or is considered lost, so a
+/// late reply can't be taken for the reply to a command written after it. A reply is considered
+/// lost when it is overdue by twice the timeout of its request: every pending request is failed,
+/// and no command is written until the replies are back in step. For that, a `whoami` is sent as
+/// a marker, and everything up to its answer is dropped, including the lost reply if it arrives
+/// after all, see [`Resync`]. A reply that is lost outright rather than late hands the replies of the commands in
+/// flight behind it to the wrong requests until the loss is noticed.
+pub struct ReturnQueue {
+    /// The pending requests in the order of their commands. The written ones come first, the
+    /// others wait for commands that aren't written yet
+    responders: VecDeque<Responder>,
+    resync: Option<Resync>,
 }
 
 impl ReturnQueue {
     pub fn new() -> Self {
         ReturnQueue {
-            queue: Vec::new(),
-            senders: HashMap::new(),
+            responders: VecDeque::new(),
+            resync: None,
         }
     }
 
@@ -45,38 +108,166 @@ impl ReturnQueue {
             return;
         }
 
-        self.queue.push(value.clone());
-        for (_, func) in self.senders.iter() {
-            let fnc = func.clone();
-            let _ = fnc.try_send(value.clone());
+        if let Some(resync) = &mut self.resync {
+            if !is_marker_reply(&value) {
+                log::debug!("Dropping {:?} while getting back in step", value);
+            } else if resync.skip > 0 {
+                log::debug!("Dropping the late reply {:?} to a whoami", value);
+                resync.skip -= 1;
+                resync.skipped_since_written |= resync.written.is_some();
+            } else if resync.written.is_some() {
+                log::info!("Back in step with the ftSwarm");
+                self.resync = None;
+            }
+            return;
+        }
+
+        match self.responders.front() {
+            Some(responder) if responder.is_written() => {
+                if let Some(responder) = self.responders.pop_front() {
+                    responder.send(Ok(value));
+                }
+            }
+            _ => log::warn!("Dropping a response nobody is waiting for: {:?}", value),
         }
     }
 
-    pub fn push_sender(&mut self, sender: &SenderHandle) {
-        self.senders.insert(sender.uid, Arc::new(sender.receiver.clone()));
+    /// Wait for the reply to the next command that is written
+    pub fn push_responder(&mut self, responder: Responder) {
+        self.responders.push_back(responder);
     }
 
-    pub fn drop_sender(&mut self, handle: &SenderHandle) {
-        self.senders.remove(&handle.uid);
+    /// `command` is written now, with the responder of its own request if it has one. Its
+    /// reply goes to the first responder that isn't waiting yet
+    pub fn written(&mut self, command: &str, responder: Option<Responder>, now: Instant) {
+        self.responders.extend(responder);
+        if let Some(responder) = self.responders.iter_mut().find(|responder| !responder.is_written()) {
+            responder.expires = Some(now + responder.timeout * 2);
+            responder.like_marker = is_marker(command);
+        }
+    }
+
+    /// Whether commands have to wait, because too many replies are outstanding, a request timed
+    /// out before its reply arrived, or the replies aren't in step
+    pub fn is_busy(&self) -> bool {
+        let written = self.responders.iter().take_while(|responder| responder.is_written());
+        let mut in_flight = 0;
+        for responder in written {
+            if responder.is_cancelled() {
+                return true;
+            }
+            in_flight += 1;
+        }
+
+        self.resync.is_some() || in_flight >= MAX_IN_FLIGHT
+    }
+
+    /// If an outstanding reply is overdue, fail every pending request and start getting back in step
+    pub fn check_lost(&mut self, now: Instant) {
+        let overdue = self.responders.iter()
+            .filter_map(|responder| responder.expires)
+            .any(|expires| now >= expires);
+        if !overdue {
+            return;
+        }
+
+        log::warn!("A response of the ftSwarm was lost, failing {} pending request(s)", self.responders.len());
+        let mut skip = 0;
+        for responder in self.responders.drain(..) {
+            if responder.like_marker {
+                skip += 1;
+            }
+            responder.send(Err(OutOfSync));
+        }
+        self.resync = Some(Resync { written: None, skip, skipped_since_written: false });
+    }
+
+    /// The marker to write, if getting back in step and it wasn't written yet
+    pub fn take_marker(&mut self, now: Instant) -> Option<String> {
+        match &mut self.resync {
+            Some(resync) if resync.written.is_none() => {
+                resync.written = Some(now);
+                Some(FtSwarmCommand::Direct(FtSwarmDirectCommand::Whoami).serialize())
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the marker wasn't answered in time, so the ftSwarm stopped answering
+    ///
+    /// If a reply that looks like its answer was skipped after it was written, the reply to a
+    /// `whoami` of a caller was lost, and the skipped one was the answer to the marker after all
+    pub fn is_marker_overdue(&mut self, now: Instant) -> bool {
+        let Some(Resync { written: Some(written), skipped_since_written, .. }) = self.resync else { return false };
+        if now < written + MARKER_TIMEOUT {
+            return false;
+        }
+
+        if skipped_since_written {
+            log::info!("Back in step with the ftSwarm");
+            self.resync = None;
+            return false;
+        }
+        true
+    }
+
+    /// When something is due if no line arrives until then
+    pub fn deadline(&self) -> Option<Instant> {
+        match &self.resync {
+            Some(resync) => resync.written.map(|written| written + MARKER_TIMEOUT),
+            None => self.responders.iter().filter_map(|responder| responder.expires).min(),
+        }
     }
 }
 
+/// Whether `command` is answered like the marker
+fn is_marker(command: &str) -> bool {
+    command.trim().eq_ignore_ascii_case(&FtSwarmDirectCommand::Whoami.serialize())
+}
+
+/// The answer to `whoami` looks like no other response, only like the answer to a `whoami` of a caller
+fn is_marker_reply(value: &S2RMessage) -> bool {
+    matches!(value, S2RMessage::RPCResponse(data) if WhoamiResponse::try_from(data.clone()).is_ok_and(|whoami| whoami.serial.is_some()))
+}
+
+/// Commands waiting to be written, in submission order
 pub struct WriteQueue {
-    queue: Vec<FtSwarmCommand>,
+    queue: VecDeque<(Option<FtSwarmCommand>, Option<Responder>)>,
 }
 
 impl WriteQueue {
     pub fn new() -> Self {
         WriteQueue {
-            queue: Vec::new(),
+            queue: VecDeque::new(),
         }
     }
 
+    /// Queue a command that doesn't expect a response
     pub fn push(&mut self, value: FtSwarmCommand) {
-        self.queue.push(value);
+        self.queue.push_back((Some(value), None));
+    }
+
+    /// Queue a command whose response is sent to `responder`
+    pub fn push_request(&mut self, value: FtSwarmCommand, responder: Responder) {
+        self.queue.push_back((Some(value), Some(responder)));
     }
 
-    pub fn pop(&mut self) -> Option<String> {
-        self.queue.pop().map(|value| value.serialize())
+    /// Queue a responder for the next response after everything queued so far
+    pub fn push_responder(&mut self, responder: Responder) {
+        self.queue.push_back((None, Some(responder)));
     }
-}
\ No newline at end of file
+
+    /// Pop the next command to write. Requests that were cancelled before being written are skipped
+    pub fn pop(&mut self) -> Option<(Option<String>, Option<Responder>)> {
+        while let Some((value, responder)) = self.queue.pop_front() {
+            if responder.as_ref().is_some_and(Responder::is_cancelled) {
+                log::debug!("Skipping cancelled request {:?}", value);
+                continue;
+            }
+
+            return Some((value.map(|value| value.serialize()), responder));
+        }
+
+        None
+    }
+}
@@ -1,9 +1,20 @@
-pub use crate::{FtSwarm, aliases};
-pub use ftswarm_serial::{SwarmSerialPort, SerialCommunication, FixedSerialPort};
+pub use crate::{FtSwarm, WhoamiResponse, aliases, lock};
+pub use crate::discovery::{Discovery, DiscoveredSwarm};
+pub use crate::cluster::SwarmCluster;
+#[cfg(feature = "config")]
+pub use crate::config::{Model, ModelConfig};
+pub use crate::error::FtSwarmError;
+pub use crate::connection::{ConnectionState, ReconnectPolicy};
+pub use ftswarm_serial::{SwarmSerialPort, AsyncSwarmSerialPort, SerialCommunication, AsyncSerialCommunication, FixedSerialPort, TcpCommunication};
 pub use crate::swarm_object::analog::*;
 pub use crate::swarm_object::digital::*;
 pub use crate::swarm_object::led::*;
 pub use crate::swarm_object::servo::*;
 pub use crate::swarm_object::controller::*;
 pub use crate::swarm_object::actor::*;
-pub use crate::swarm_object::{NewSwarmObject, SwarmObject, Hysteresis, NormallyOpen, Io};
+pub use crate::swarm_object::joystick::*;
+pub use crate::swarm_object::stepper::*;
+pub use crate::swarm_object::watch::{ValueChange, ValueWatch};
+pub use crate::swarm_object::{NewSwarmObject, SwarmObject, TriggerTarget, Hysteresis, NormallyOpen, Io};
+pub use ftswarm_proto::command::enums::TriggerEvent;
+pub use ftswarm_proto::message_parser::user_event::UserEvent;
@@ -0,0 +1,175 @@
+//! Leaving the ftSwarm in a safe state when the program ends
+//!
+//! When the handle returned by e.g. [`FtSwarm::new`] is dropped, the registered shutdown actions
+//! run and the ftSwarm is halted before `drop` returns. On a runtime with a single thread, the
+//! actions can't run while `drop` blocks, so only the `halt` is written. Call
+//! [`FtSwarm::shutdown`] before leaving `main` to have the actions run in any case.
+//!
+//! ```no_run
+//! use ftswarm::prelude::*;
+//!
+//! # async fn example() -> Result<(), FtSwarmError> {
+//! let swarm = FtSwarm::connect_first().await?;
+//! let servo = Servo::create(&swarm, "SERVO1", ()).await;
+//! let servo = lock(&servo).await.clone();
+//!
+//! // Park the servo before the ftSwarm is halted
+//! swarm.on_shutdown(move |_| {
+//!     let servo = servo.clone();
+//!     async move { let _ = servo.set_position(0).await; }
+//! });
+//! let ctrl_c = swarm.shutdown_on_ctrl_c();
+//!
+//! // Halt the ftSwarm if the control loop hangs for more than a second
+//! let watchdog = swarm.watchdog(std::time::Duration::from_secs(1));
+//! while !ctrl_c.is_finished() {
+//!     // ... talk to the ftSwarm, or call `watchdog.feed()` while idling on purpose
+//! #   break;
+//! }
+//!
+//! swarm.shutdown().await;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant};
+use ftswarm_proto::command::direct::FtSwarmDirectCommand;
+use ftswarm_proto::command::FtSwarmCommand;
+use ftswarm_proto::Serialized;
+use ftswarm_serial::BlockingWriter;
+use crate::FtSwarm;
+
+/// How long the shutdown actions may take together by default
+pub const DEFAULT_SHUTDOWN_DEADLINE: Duration = Duration::from_secs(1);
+
+/// How long writing `halt` may block while the connection is dropped
+const BLOCKING_HALT_TIMEOUT: Duration = Duration::from_millis(500);
+
+type ShutdownAction = Box<dyn FnOnce(FtSwarm) -> Pin<Box<dyn Future<Output=()> + Send>> + Send>;
+
+/// What happens when the connection is shut down, shared by all clones of an `FtSwarm`
+pub(crate) struct Lifecycle {
+    actions: std::sync::Mutex<Vec<ShutdownAction>>,
+    deadline: std::sync::Mutex<Duration>,
+    /// When a command was last sent on behalf of the program
+    last_activity: std::sync::Mutex<Instant>,
+    /// Writes to the current port without the runtime, if it has its own thread
+    writer: std::sync::Mutex<Option<BlockingWriter>>,
+}
+
+impl Lifecycle {
+    pub fn new() -> Self {
+        Lifecycle {
+            actions: std::sync::Mutex::new(Vec::new()),
+            deadline: std::sync::Mutex::new(DEFAULT_SHUTDOWN_DEADLINE),
+            last_activity: std::sync::Mutex::new(Instant::now()),
+            writer: std::sync::Mutex::new(None),
+        }
+    }
+
+    pub fn push_action(&self, action: ShutdownAction) {
+        self.actions.lock().unwrap().push(action);
+    }
+
+    pub fn set_deadline(&self, deadline: Duration) {
+        *self.deadline.lock().unwrap() = deadline;
+    }
+
+    pub fn deadline(&self) -> Duration {
+        *self.deadline.lock().unwrap()
+    }
+
+    pub fn set_writer(&self, writer: Option<BlockingWriter>) {
+        *self.writer.lock().unwrap() = writer;
+    }
+
+    pub fn touch(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+    }
+
+    fn last_activity(&self) -> Instant {
+        *self.last_activity.lock().unwrap()
+    }
+
+    /// Run the actions in the order they were registered, each only once. Returns whether
+    /// they finished before the deadline
+    pub async fn run_actions(&self, swarm: &FtSwarm) -> bool {
+        let actions = std::mem::take(&mut *self.actions.lock().unwrap());
+        let deadline = *self.deadline.lock().unwrap();
+
+        let run = async {
+            for action in actions {
+                action(swarm.clone()).await;
+            }
+        };
+
+        tokio::time::timeout(deadline, run).await.is_ok()
+    }
+
+    /// Write `halt` without the runtime, skipping the actions. Returns whether the port allowed it
+    pub fn halt_blocking(&self) -> bool {
+        let Some(writer) = self.writer.lock().unwrap().clone() else { return false };
+
+        if !self.actions.lock().unwrap().is_empty() {
+            log::warn!("Skipping the shutdown actions, call FtSwarm::shutdown to run them");
+        }
+        if let Err(err) = writer.write_line(FtSwarmCommand::Direct(FtSwarmDirectCommand::Halt).serialize(), BLOCKING_HALT_TIMEOUT) {
+            log::error!("Failed to halt the ftSwarm: {}", err);
+        }
+        true
+    }
+}
+
+/// Halts the ftSwarm if no command was sent for too long, see [`FtSwarm::watchdog`]
+///
+/// The watchdog runs on this computer, so it catches a control loop that hangs, but not a
+/// program that is killed. It stops when dropped.
+#[must_use = "the watchdog stops when dropped"]
+pub struct Watchdog {
+    lifecycle: Arc<Lifecycle>,
+    task: JoinHandle<()>,
+}
+
+impl Watchdog {
+    pub(crate) fn start(swarm: FtSwarm, lifecycle: Arc<Lifecycle>, timeout: Duration) -> Self {
+        let task = {
+            let lifecycle = lifecycle.clone();
+            tokio::spawn(async move {
+                // Halt once per silence, not again and again until a command is sent
+                let mut halted_after = None;
+                loop {
+                    let last_activity = lifecycle.last_activity();
+                    let due = last_activity + timeout;
+                    if Instant::now() < due {
+                        tokio::time::sleep_until(due).await;
+                        continue;
+                    }
+
+                    if halted_after != Some(last_activity) {
+                        log::warn!("No command was sent for {:?}, halting the ftSwarm", timeout);
+                        swarm.queue_halt().await;
+                        halted_after = Some(last_activity);
+                    }
+                    tokio::time::sleep(timeout).await;
+                }
+            })
+        };
+
+        Watchdog { lifecycle, task }
+    }
+
+    /// Count as activity without sending a command, for a program that is idle on purpose
+    pub fn feed(&self) {
+        self.lifecycle.touch();
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
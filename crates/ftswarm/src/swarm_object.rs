@@ -1,9 +1,10 @@
 use std::{future::Future, sync::Arc};
 
 use ftswarm_macros::Updateable;
-use ftswarm_proto::{command::{argument::Argument, rpc::{FtSwarmRPCCommand, RpcFunction}, FtSwarmCommand}, message_parser::rpc::RPCReturnParam};
+use ftswarm_proto::{command::{argument::Argument, enums::{IOType, TriggerEvent}, rpc::{FtSwarmRPCCommand, RpcFunction}, FtSwarmCommand}, error::ProtoError, message_parser::rpc::RPCReturnParam};
 
 use crate::{lock, FtSwarm, Mutex};
+use crate::error::FtSwarmError;
 
 pub mod analog;
 pub mod digital;
@@ -12,6 +13,9 @@ pub mod servo;
 pub mod actor;
 pub mod led;
 pub mod controller;
+pub mod joystick;
+pub mod stepper;
+pub mod watch;
 
 
 pub type Io<T> = Arc<Mutex<Box<T>>>;
@@ -22,39 +26,60 @@ pub trait Updateable {
 
 pub trait NewSwarmObject<Params> {
     fn new(name: &str, swarm: FtSwarm, params: Params) -> Box<Self>;
-    fn init(&mut self) -> impl Future<Output=()> {
-        async move {}
+    fn init(&mut self) -> impl Future<Output=Result<(), FtSwarmError>> {
+        async move { Ok(()) }
     }
     fn name(&self) -> &str;
     fn swarm(&self) -> &FtSwarm;
 }
 
 pub trait SwarmObject<Params>: NewSwarmObject<Params> + Updateable + Clone + Sync + Send {
+    /// Create and set up the object, panicking if the setup fails
     fn create(swarm: &FtSwarm, name: &str, params: Params) -> impl Future<Output=Io<Self>>
     where
         Self: 'static,
     {
-        let obj = Self::new(name, swarm.clone(), params);
-        let arc = Arc::new(Mutex::new(obj));
-        let for_closure = arc.clone();
+        let created = Self::try_create(swarm, name, params);
 
         async move {
-            swarm.push_cache(Box::new(move |subscription| {
-                let for_task = for_closure.clone();
-                tokio::spawn(async move {
+            created.await.unwrap_or_else(|err| panic!("{}", err))
+        }
+    }
+
+    /// Create and set up the object. If the setup fails, the object isn't registered
+    fn try_create(swarm: &FtSwarm, name: &str, params: Params) -> impl Future<Output=Result<Io<Self>, FtSwarmError>>
+    where
+        Self: 'static,
+    {
+        let mut obj = Self::new(name, swarm.clone(), params);
+
+        async move {
+            // An object of the same name keeps its setup if this one fails
+            let previous = swarm.setup_of(name).await;
+            if let Err(err) = obj.init().await {
+                swarm.restore_setup(name, previous).await;
+                return Err(err);
+            }
+
+            let arc = Arc::new(Mutex::new(obj));
+
+            // A single task per object applies the updates, so they are seen in the order they arrived
+            let (updates, mut received) = tokio::sync::mpsc::unbounded_channel();
+            let for_task = arc.clone();
+            tokio::spawn(async move {
+                while let Some(subscription) = received.recv().await {
                     let mut obj = lock(&for_task).await;
                     obj.handle_subscription(&subscription);
-                });
+                }
+            });
+            swarm.push_cache(Box::new(move |subscription| {
+                let _ = updates.send(subscription);
             }), name).await;
-            {
-                let mut obj = lock(&arc).await;
-                obj.init().await;
-            }
-            arc
+            Ok(arc)
         }
     }
 
-    fn run_command(&self, func: RpcFunction, args: Vec<Argument>) -> impl Future<Output=Result<RPCReturnParam, String>> {
+    fn run_command(&self, func: RpcFunction, args: Vec<Argument>) -> impl Future<Output=Result<RPCReturnParam, FtSwarmError>> {
         let command = FtSwarmRPCCommand {
             target: self.name().to_string(),
             function: func,
@@ -63,6 +88,97 @@ pub trait SwarmObject<Params>: NewSwarmObject<Params> + Updateable + Clone + Syn
 
         self.swarm().transact(FtSwarmCommand::RPC(command))
     }
+
+    /// Like `run_command`, but errors are reported as a failed setup step of this object.
+    /// The command is sent again when the connection is restored after a reconnect
+    fn run_setup_command(&self, func: RpcFunction, args: Vec<Argument>) -> impl Future<Output=Result<RPCReturnParam, FtSwarmError>> {
+        let command = FtSwarmRPCCommand {
+            target: self.name().to_string(),
+            function: func,
+            args,
+        };
+        let swarm = self.swarm().clone();
+
+        async move {
+            match swarm.transact(FtSwarmCommand::RPC(command.clone())).await {
+                Ok(response) => {
+                    swarm.record_setup(command).await;
+                    Ok(response)
+                }
+                Err(source) => Err(FtSwarmError::Setup {
+                    object: command.target,
+                    step: command.function,
+                    source: Box::new(source),
+                }),
+            }
+        }
+    }
+
+    /// Read which kind of IO is behind this port
+    fn get_io_type(&self) -> impl Future<Output=Result<IOType, FtSwarmError>> {
+        get_typed(self, RpcFunction::GetIOType)
+    }
+}
+
+/// Run a getter that answers with the id of an enum, e.g. `getSensorType`
+pub(crate) async fn get_typed<Params, Object, T>(object: &Object, function: RpcFunction) -> Result<T, FtSwarmError>
+where
+    Object: SwarmObject<Params>,
+    T: TryFrom<i32, Error=ProtoError>,
+{
+    let id = object.run_command(function, vec![])
+        .await
+        .and_then(|response| response.as_int().ok_or_else(|| FtSwarmError::unexpected("Invalid response")))?;
+
+    Ok(T::try_from(id)?)
+}
+
+/// An output that an input can drive directly on the controller, see e.g. [`Switch::on_trigger`](digital::Switch::on_trigger)
+pub trait TriggerTarget {
+    fn target_name(&self) -> &str;
+}
+
+impl<T: TriggerTarget + ?Sized> TriggerTarget for Box<T> {
+    fn target_name(&self) -> &str {
+        (**self).target_name()
+    }
+}
+
+/// Let the controller drive `actor` when `object` fires `event`. Without `value`, the
+/// actor is set to the value of the input.
+///
+/// Triggers are restored after a reconnect, like the rest of the setup
+pub(crate) async fn set_trigger<Params, Object: SwarmObject<Params>>(
+    object: &Object,
+    function: RpcFunction,
+    event: TriggerEvent,
+    actor: &impl TriggerTarget,
+    value: Option<i32>,
+) -> Result<(), FtSwarmError> {
+    let mut args = vec![Argument::TriggerEvent(event), Argument::Actor(actor.target_name().to_string())];
+    if let Some(value) = value {
+        args.push(Argument::Int(value as i64));
+    }
+
+    run_recorded_command(object, function, args).await
+}
+
+/// Run a command that changes how the port behaves, and send it again after a reconnect.
+/// Unlike a setup step, errors are returned as they are
+pub(crate) async fn run_recorded_command<Params, Object: SwarmObject<Params>>(
+    object: &Object,
+    function: RpcFunction,
+    args: Vec<Argument>,
+) -> Result<(), FtSwarmError> {
+    let command = FtSwarmRPCCommand {
+        target: object.name().to_string(),
+        function,
+        args,
+    };
+
+    object.swarm().transact(FtSwarmCommand::RPC(command.clone())).await?;
+    object.swarm().record_setup(command).await;
+    Ok(())
 }
 
 #[derive(Clone)]
@@ -75,11 +191,11 @@ pub enum NormallyOpen {
     Closed,
 }
 
-impl Into<Argument> for NormallyOpen {
-    fn into(self) -> Argument {
-        match self {
-            NormallyOpen::Open => Argument::Int(0),
-            NormallyOpen::Closed => Argument::Int(1),
+impl From<NormallyOpen> for Argument {
+    fn from(value: NormallyOpen) -> Self {
+        match value {
+            NormallyOpen::Open => Argument::Bool(false),
+            NormallyOpen::Closed => Argument::Bool(true),
         }
     }
 }
\ No newline at end of file
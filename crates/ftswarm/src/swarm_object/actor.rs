@@ -1,10 +1,11 @@
 use std::future::Future;
 use ftswarm_macros::{default_new_swarm_object_impls, impl_swarm_object};
 use ftswarm_proto::command::argument::Argument;
-use ftswarm_proto::command::enums::ActorType;
+use ftswarm_proto::command::enums::{ActorType, MotionType};
 use ftswarm_proto::command::rpc::RpcFunction;
 use crate::FtSwarm;
-use crate::swarm_object::{NewSwarmObject, SwarmObject, Updateable};
+use crate::error::FtSwarmError;
+use crate::swarm_object::{get_typed, run_recorded_command, NewSwarmObject, SwarmObject, TriggerTarget, Updateable};
 use ftswarm_macros::actor_swarm_object;
 
 
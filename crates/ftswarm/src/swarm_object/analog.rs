@@ -1,11 +1,14 @@
 use std::future::Future;
-use ftswarm_macros::{default_new_swarm_object_impls, impl_swarm_object, impl_int_updateable};
+use ftswarm_macros::{default_new_swarm_object_impls, impl_swarm_object, impl_int_updateable, impl_input_object};
 use ftswarm_proto::command::argument::Argument;
-use ftswarm_proto::command::enums::SensorType;
+use ftswarm_proto::command::enums::{SensorType, TriggerEvent};
 use ftswarm_proto::command::rpc::RpcFunction;
 use ftswarm_proto::message_parser::rpc::RPCReturnParam;
 use crate::FtSwarm;
-use crate::swarm_object::{Hysteresis, NewSwarmObject, NormallyOpen, SwarmObject, Updateable};
+use crate::error::FtSwarmError;
+use crate::swarm_object::{get_typed, set_trigger, Hysteresis, NewSwarmObject, NormallyOpen, SwarmObject, TriggerTarget, Updateable};
+use crate::swarm_object::watch::{ValueChange, ValueWatch};
+use tokio_stream::Stream;
 use ftswarm_macros::analog_swarm_object;
 
 analog_swarm_object!(Analog);
@@ -18,37 +21,37 @@ analog_swarm_object!(Ultrasonic);
 analog_swarm_object!(Voltmeter);
 
 impl Thermometer {
-    pub async fn get_kelvin(&self) -> Result<f32, String> {
+    pub async fn get_kelvin(&self) -> Result<f32, FtSwarmError> {
         self.run_command(RpcFunction::GetKelvin, vec![])
             .await
-            .and_then(|param| param.as_float().ok_or("Failed to get kelvin".to_string()))
+            .and_then(|param| param.as_float().ok_or_else(|| FtSwarmError::unexpected("Failed to get kelvin")))
     }
 
-    pub async fn get_celsius(&self) -> Result<f32, String> {
+    pub async fn get_celsius(&self) -> Result<f32, FtSwarmError> {
         self.run_command(RpcFunction::GetCelsius, vec![])
             .await
-            .and_then(|param| param.as_float().ok_or("Failed to get celsius".to_string()))
+            .and_then(|param| param.as_float().ok_or_else(|| FtSwarmError::unexpected("Failed to get celsius")))
     }
 
-    pub async fn get_fahrenheit(&self) -> Result<f32, String> {
+    pub async fn get_fahrenheit(&self) -> Result<f32, FtSwarmError> {
         self.run_command(RpcFunction::GetFahrenheit, vec![])
             .await
-            .and_then(|param| param.as_float().ok_or("Failed to get fahrenheit".to_string()))
+            .and_then(|param| param.as_float().ok_or_else(|| FtSwarmError::unexpected("Failed to get fahrenheit")))
     }
 }
 
 impl Ohmmeter {
-    pub async fn get_resistance(&self) -> Result<f32, String> {
+    pub async fn get_resistance(&self) -> Result<f32, FtSwarmError> {
         self.run_command(RpcFunction::GetResistance, vec![])
             .await
-            .and_then(|param| param.as_float().ok_or("Failed to get resistance".to_string()))
+            .and_then(|param| param.as_float().ok_or_else(|| FtSwarmError::unexpected("Failed to get resistance")))
     }
 }
 
 impl Voltmeter {
-    pub async fn get_voltage(&self) -> Result<f32, String> {
+    pub async fn get_voltage(&self) -> Result<f32, FtSwarmError> {
         self.run_command(RpcFunction::GetVoltage, vec![])
             .await
-            .and_then(|param| param.as_float().ok_or("Failed to get voltage".to_string()))
+            .and_then(|param| param.as_float().ok_or_else(|| FtSwarmError::unexpected("Failed to get voltage")))
     }
 }
\ No newline at end of file
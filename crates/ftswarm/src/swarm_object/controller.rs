@@ -2,8 +2,10 @@ use ftswarm_macros::{default_new_swarm_object_impls, impl_swarm_object};
 use ftswarm_proto::command::argument::Argument;
 use ftswarm_proto::command::enums::MicroStepMode;
 use ftswarm_proto::command::rpc::RpcFunction;
+use ftswarm_proto::message_parser::user_event::MAX_USER_EVENT_PARAMS;
 use crate::FtSwarm;
-use crate::swarm_object::{NewSwarmObject, SwarmObject, Updateable};
+use crate::error::FtSwarmError;
+use crate::swarm_object::{get_typed, NewSwarmObject, SwarmObject, Updateable};
 
 #[derive(Updateable, Clone)]
 pub struct Controller {
@@ -25,27 +27,48 @@ impl NewSwarmObject<()> for Controller {
 }
 
 impl Controller {
-    pub async fn show(&self) -> Result<(), String> {
+    pub async fn show(&self) -> Result<(), FtSwarmError> {
         self.run_command(RpcFunction::Show, vec![])
             .await
             .map(|_| ())
     }
 
-    pub async fn set_micro_step_mode(&self, mode: MicroStepMode) -> Result<(), String> {
+    pub async fn set_micro_step_mode(&self, mode: MicroStepMode) -> Result<(), FtSwarmError> {
         self.run_command(RpcFunction::SetMicroStepMode, vec![Argument::MicroStepMode(mode)])
             .await
             .map(|_| ())
     }
 
-    pub async fn set_register(&self, register: u8, value: u32) -> Result<(), String> {
+    /// Read back the micro step mode (ftSwarmPwrDrive only)
+    pub async fn get_micro_step_mode(&self) -> Result<MicroStepMode, FtSwarmError> {
+        get_typed(self, RpcFunction::GetMicroStepMode).await
+    }
+
+    /// Trigger a user event on the ftSwarm with up to 10 parameters, which have to fit into 32 bits
+    pub async fn trigger_user_event(&self, params: &[i64]) -> Result<(), FtSwarmError> {
+        if params.len() > MAX_USER_EVENT_PARAMS {
+            return Err(FtSwarmError::InvalidArgument(format!("A user event takes at most {} parameters, got {}", MAX_USER_EVENT_PARAMS, params.len())));
+        }
+
+        if let Some(param) = params.iter().find(|param| i32::try_from(**param).is_err()) {
+            return Err(FtSwarmError::InvalidArgument(format!("User event parameter {} doesn't fit into 32 bits", param)));
+        }
+
+        let args = params.iter().map(|param| Argument::Int(*param)).collect();
+        self.run_command(RpcFunction::TriggerUserEvent, args)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn set_register(&self, register: u8, value: u32) -> Result<(), FtSwarmError> {
         self.run_command(RpcFunction::SetRegister, vec![Argument::Int(register as i64), Argument::Int(value as i64)])
             .await
             .map(|_| ())
     }
 
-    pub async fn get_register(&self, register: u8) -> Result<u32, String> {
+    pub async fn get_register(&self, register: u8) -> Result<u32, FtSwarmError> {
         self.run_command(RpcFunction::GetRegister, vec![Argument::Int(register as i64)])
             .await
-            .and_then(|response| response.as_int().map(|value| value as u32).ok_or("Invalid response".to_string()))
+            .and_then(|response| response.as_int().map(|value| value as u32).ok_or_else(|| FtSwarmError::unexpected("Invalid response")))
     }
 }
@@ -1,12 +1,15 @@
 use std::future::Future;
-use ftswarm_macros::{default_new_swarm_object_impls, impl_swarm_object, impl_bool_updateable, impl_int_updateable};
+use ftswarm_macros::{default_new_swarm_object_impls, impl_swarm_object, impl_bool_updateable, impl_int_updateable, impl_input_object};
 use ftswarm_proto::command::argument::Argument;
-use ftswarm_proto::command::enums::SensorType;
+use ftswarm_proto::command::enums::{SensorType, TriggerEvent};
 use ftswarm_proto::command::rpc::RpcFunction;
 use ftswarm_proto::message_parser::rpc::RPCReturnParam;
 use crate::proto::command::enums::ToggleType;
 use crate::FtSwarm;
-use crate::swarm_object::{NewSwarmObject, NormallyOpen, SwarmObject, Updateable};
+use crate::error::FtSwarmError;
+use crate::swarm_object::{get_typed, set_trigger, NewSwarmObject, NormallyOpen, SwarmObject, TriggerTarget, Updateable};
+use crate::swarm_object::watch::{ValueChange, ValueWatch};
+use tokio_stream::Stream;
 use ftswarm_macros::digital_swarm_object;
 
 digital_swarm_object!(Digital, false);
@@ -20,6 +23,7 @@ digital_swarm_object!(Switch, true);
 pub struct RotaryEncoder {
     pub name: String,
     pub value: i32,
+    values: ValueWatch<i32>,
     should_subscribe: bool,
     normally_open: NormallyOpen,
     swarm: FtSwarm,
@@ -29,16 +33,19 @@ impl_swarm_object!(RotaryEncoder, bool );
 impl NewSwarmObject<bool> for RotaryEncoder {
     default_new_swarm_object_impls!();
     fn new(name: &str, swarm: FtSwarm, should_subscribe: bool) -> Box<Self> {
-        Box::new(RotaryEncoder { name: name.to_string(), value: 0, should_subscribe, normally_open: NormallyOpen::Closed, swarm })
+        Box::new(RotaryEncoder { name: name.to_string(), value: 0, values: ValueWatch::new(0), should_subscribe, normally_open: NormallyOpen::Closed, swarm })
     }
 
-    fn init(&mut self) -> impl Future<Output=()> {
-        async move {
-            self.run_command(RpcFunction::SetSensorType, vec![Argument::SensorType(SensorType::RotaryEncoder), self.normally_open.clone().into()]).await.unwrap();
-            if self.should_subscribe {
-                self.run_command(RpcFunction::Subscribe, vec![Argument::Int(0i64)]).await.unwrap();
-            }
-            self.value = self.run_command(RpcFunction::GetValue, vec![]).await.ok().and_then(|param| param.as_int()).unwrap_or(0);
+    async fn init(&mut self) -> Result<(), FtSwarmError> {
+        self.run_setup_command(RpcFunction::SetSensorType, vec![Argument::SensorType(SensorType::RotaryEncoder), self.normally_open.clone().into()]).await?;
+        if self.should_subscribe {
+            self.run_setup_command(RpcFunction::Subscribe, vec![Argument::Int(0i64)]).await?;
         }
+        self.value = self.run_command(RpcFunction::GetValue, vec![]).await.ok().and_then(|param| param.as_int()).unwrap_or(0);
+        self.values.update(self.value);
+
+        Ok(())
     }
 }
+
+impl_input_object!(RotaryEncoder);
@@ -0,0 +1,137 @@
+use ftswarm_macros::{default_new_swarm_object_impls, impl_swarm_object};
+use ftswarm_proto::command::argument::Argument;
+use ftswarm_proto::command::enums::TriggerEvent;
+use ftswarm_proto::command::rpc::RpcFunction;
+use ftswarm_proto::message_parser::rpc::RPCReturnParam;
+use tokio_stream::Stream;
+use crate::FtSwarm;
+use crate::error::FtSwarmError;
+use crate::swarm_object::{set_trigger, Hysteresis, NewSwarmObject, SwarmObject, TriggerTarget, Updateable};
+use crate::swarm_object::watch::{ValueChange, ValueWatch};
+
+/// The deflection of both joystick axes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct JoystickPosition {
+    /// Left (negative) to right (positive)
+    pub lr: i32,
+    /// Back (negative) to front (positive)
+    pub fb: i32,
+}
+
+impl JoystickPosition {
+    pub fn new(lr: i32, fb: i32) -> Self {
+        JoystickPosition { lr, fb }
+    }
+}
+
+impl TryFrom<&RPCReturnParam> for JoystickPosition {
+    type Error = FtSwarmError;
+
+    /// Joysticks report both axes at once, e.g. `-12 40` (left/right first)
+    fn try_from(value: &RPCReturnParam) -> Result<Self, Self::Error> {
+        let invalid = || FtSwarmError::unexpected(&format!("Invalid joystick value {:?}", value));
+        let payload = value.as_string().ok_or_else(invalid)?;
+
+        let mut axes = payload.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .map(|part| part.parse::<i32>());
+
+        match (axes.next(), axes.next(), axes.next()) {
+            (Some(Ok(lr)), Some(Ok(fb)), None) => Ok(JoystickPosition::new(lr, fb)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A joystick (`JOY1`, `JOY2`)
+#[derive(Clone)]
+pub struct Joystick {
+    pub name: String,
+    pub hysteresis: Hysteresis,
+    pub value: JoystickPosition,
+    values: ValueWatch<JoystickPosition>,
+    swarm: FtSwarm,
+}
+
+impl_swarm_object!(Joystick, Hysteresis);
+
+impl NewSwarmObject<Hysteresis> for Joystick {
+    default_new_swarm_object_impls!();
+
+    fn new(name: &str, swarm: FtSwarm, hysteresis: Hysteresis) -> Box<Self> {
+        Box::new(Joystick {
+            name: name.to_string(),
+            hysteresis,
+            value: JoystickPosition::default(),
+            values: ValueWatch::new(JoystickPosition::default()),
+            swarm,
+        })
+    }
+
+    async fn init(&mut self) -> Result<(), FtSwarmError> {
+        self.run_setup_command(RpcFunction::Subscribe, vec![Argument::Int(self.hysteresis.0 as i64)]).await?;
+
+        if let Ok(value) = self.read_value().await {
+            self.value = value;
+            self.values.update(value);
+        }
+
+        Ok(())
+    }
+}
+
+impl Updateable for Joystick {
+    fn handle_subscription(&mut self, message: &RPCReturnParam) {
+        match JoystickPosition::try_from(message) {
+            Ok(value) => {
+                self.value = value;
+                self.values.update(value);
+            }
+            Err(err) => log::warn!("Ignoring update of {}: {}", self.name, err),
+        }
+    }
+}
+
+impl Joystick {
+    /// The last position reported by the ftSwarm
+    pub async fn get_value(&self) -> JoystickPosition {
+        self.value
+    }
+
+    /// Ask the ftSwarm for the current position
+    pub async fn read_value(&self) -> Result<JoystickPosition, FtSwarmError> {
+        self.run_command(RpcFunction::GetValue, vec![])
+            .await
+            .and_then(|param| JoystickPosition::try_from(&param))
+    }
+
+    /// Let the controller set `actor` to `value` when `event` occurs on the left/right axis
+    pub async fn on_trigger_lr(&self, event: TriggerEvent, actor: &impl TriggerTarget, value: i32) -> Result<(), FtSwarmError> {
+        set_trigger(self, RpcFunction::OnTriggerLR, event, actor, Some(value)).await
+    }
+
+    /// Like `on_trigger_lr`, but `actor` is set to the left/right deflection
+    pub async fn on_trigger_lr_forward(&self, event: TriggerEvent, actor: &impl TriggerTarget) -> Result<(), FtSwarmError> {
+        set_trigger(self, RpcFunction::OnTriggerLR, event, actor, None).await
+    }
+
+    /// Let the controller set `actor` to `value` when `event` occurs on the front/back axis
+    pub async fn on_trigger_fb(&self, event: TriggerEvent, actor: &impl TriggerTarget, value: i32) -> Result<(), FtSwarmError> {
+        set_trigger(self, RpcFunction::OnTriggerFB, event, actor, Some(value)).await
+    }
+
+    /// Like `on_trigger_fb`, but `actor` is set to the front/back deflection
+    pub async fn on_trigger_fb_forward(&self, event: TriggerEvent, actor: &impl TriggerTarget) -> Result<(), FtSwarmError> {
+        set_trigger(self, RpcFunction::OnTriggerFB, event, actor, None).await
+    }
+
+    /// Follow the position without locking this object
+    pub fn watch(&self) -> tokio::sync::watch::Receiver<ValueChange<JoystickPosition>> {
+        self.values.watch()
+    }
+
+    /// The current position, then every change
+    pub fn changes(&self) -> impl Stream<Item=ValueChange<JoystickPosition>> {
+        self.values.changes()
+    }
+}
@@ -2,7 +2,8 @@ use ftswarm_macros::{default_new_swarm_object_impls, impl_swarm_object};
 use ftswarm_proto::command::argument::Argument;
 use ftswarm_proto::command::rpc::RpcFunction;
 use crate::FtSwarm;
-use crate::swarm_object::{NewSwarmObject, SwarmObject, Updateable};
+use crate::error::FtSwarmError;
+use crate::swarm_object::{NewSwarmObject, SwarmObject, TriggerTarget, Updateable};
 
 #[derive(Updateable, Clone)]
 pub struct Led {
@@ -139,21 +140,43 @@ impl From<String> for LedColor {
     }
 }
 
-impl Into<i64> for LedColor {
-    fn into(self) -> i64 {
-        ((self.red << 16) | (self.green << 8) | self.blue) as i64
+impl From<LedColor> for i64 {
+    fn from(color: LedColor) -> Self {
+        ((color.red << 16) | (color.green << 8) | color.blue) as i64
+    }
+}
+
+impl From<i64> for LedColor {
+    fn from(value: i64) -> Self {
+        LedColor::new(((value >> 16) & 0xff) as i32, ((value >> 8) & 0xff) as i32, (value & 0xff) as i32)
+    }
+}
+
+impl TriggerTarget for Led {
+    fn target_name(&self) -> &str {
+        &self.name
     }
 }
 
 impl Led {
-    pub async fn set_color(&self, color: LedColor) -> Result<(), String> {
+    pub async fn set_color(&self, color: LedColor) -> Result<(), FtSwarmError> {
         self.run_command(RpcFunction::SetColor, vec![Argument::Int(color.into())]).await
         .map(|_| ())
     }
 
-    pub async fn set_brightness(&self, brightness: i32) -> Result<(), String> {
-        let brightness = brightness.min(255).max(0);
+    pub async fn set_brightness(&self, brightness: i32) -> Result<(), FtSwarmError> {
+        let brightness = brightness.clamp(0, 255);
         self.run_command(RpcFunction::SetBrightness, vec![Argument::Int(brightness as i64)]).await
         .map(|_| ())
     }
+
+    pub async fn get_color(&self) -> Result<LedColor, FtSwarmError> {
+        self.run_command(RpcFunction::GetColor, vec![]).await
+        .and_then(|response| response.as_int().map(|color| LedColor::from(color as i64)).ok_or_else(|| FtSwarmError::unexpected("Invalid color")))
+    }
+
+    pub async fn get_brightness(&self) -> Result<i32, FtSwarmError> {
+        self.run_command(RpcFunction::GetBrightness, vec![]).await
+        .and_then(|response| response.as_int().ok_or_else(|| FtSwarmError::unexpected("Invalid brightness")))
+    }
 }
@@ -2,7 +2,8 @@ use ftswarm_macros::{default_new_swarm_object_impls, impl_swarm_object};
 use ftswarm_proto::command::argument::Argument;
 use ftswarm_proto::command::rpc::RpcFunction;
 use crate::FtSwarm;
-use crate::swarm_object::{NewSwarmObject, SwarmObject, Updateable};
+use crate::error::FtSwarmError;
+use crate::swarm_object::{NewSwarmObject, SwarmObject, TriggerTarget, Updateable};
 
 #[derive(Updateable, Clone)]
 pub struct Servo {
@@ -23,26 +24,32 @@ impl NewSwarmObject<()> for Servo {
     default_new_swarm_object_impls!();
 }
 
+impl TriggerTarget for Servo {
+    fn target_name(&self) -> &str {
+        &self.name
+    }
+}
+
 impl Servo {
-    pub async fn get_position(&self) -> Result<i32, String> {
+    pub async fn get_position(&self) -> Result<i32, FtSwarmError> {
         self.run_command(RpcFunction::GetPosition, vec![])
             .await
-            .and_then(|param| param.as_int().ok_or("Invalid response".to_string()))
+            .and_then(|param| param.as_int().ok_or_else(|| FtSwarmError::unexpected("Invalid response")))
     }
 
-    pub async fn set_position(&self, position: i32) -> Result<(), String> {
+    pub async fn set_position(&self, position: i32) -> Result<(), FtSwarmError> {
         self.run_command(RpcFunction::SetPosition, vec![Argument::Int(position as i64)])
             .await
             .map(|_| ())
     }
 
-    pub async fn get_offset(&self) -> Result<i32, String> {
+    pub async fn get_offset(&self) -> Result<i32, FtSwarmError> {
         self.run_command(RpcFunction::GetOffset, vec![])
             .await
-            .and_then(|param| param.as_int().ok_or("Invalid response".to_string()))
+            .and_then(|param| param.as_int().ok_or_else(|| FtSwarmError::unexpected("Invalid response")))
     }
 
-    pub async fn set_offset(&self, offset: i32) -> Result<(), String> {
+    pub async fn set_offset(&self, offset: i32) -> Result<(), FtSwarmError> {
         self.run_command(RpcFunction::SetOffset, vec![Argument::Int(offset as i64)])
             .await
             .map(|_| ())
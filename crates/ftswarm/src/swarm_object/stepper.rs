@@ -0,0 +1,151 @@
+use ftswarm_macros::{default_new_swarm_object_impls, impl_swarm_object};
+use ftswarm_proto::command::argument::Argument;
+use ftswarm_proto::command::enums::{ActorType, MotionType};
+use ftswarm_proto::command::rpc::RpcFunction;
+use std::pin::pin;
+use tokio::time::Duration;
+use tokio_stream::{Stream, StreamExt};
+use crate::FtSwarm;
+use crate::error::FtSwarmError;
+use crate::swarm_object::{get_typed, run_recorded_command, NewSwarmObject, SwarmObject, TriggerTarget, Updateable};
+use crate::swarm_object::watch::ValueChange;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+impl Direction {
+    fn apply(self, speed: u32) -> i64 {
+        match self {
+            Direction::Forward => speed as i64,
+            Direction::Backward => -(speed as i64),
+        }
+    }
+}
+
+/// A stepper motor on an ftSwarmPwrDrive (`M1`..`M4`)
+///
+/// The micro step mode is set for the whole board, see [`Controller::set_micro_step_mode`](crate::swarm_object::controller::Controller::set_micro_step_mode).
+/// Positions are counted in (micro) steps.
+///
+/// The firmware has no command to move by or to a number of steps, so a stepper can't be given
+/// a relative or absolute target yet: it runs at its speed until it is braked.
+#[derive(Updateable, Clone)]
+pub struct Stepper {
+    pub name: String,
+    swarm: FtSwarm,
+}
+
+impl_swarm_object!(Stepper, ());
+
+impl NewSwarmObject<()> for Stepper {
+    default_new_swarm_object_impls!();
+
+    fn new(name: &str, swarm: FtSwarm, _params: ()) -> Box<Self> {
+        Box::new(Stepper {
+            name: name.to_string(),
+            swarm,
+        })
+    }
+
+    async fn init(&mut self) -> Result<(), FtSwarmError> {
+        self.run_setup_command(RpcFunction::SetActorType, vec![Argument::ActorType(ActorType::Stepper)]).await?;
+        Ok(())
+    }
+}
+
+impl TriggerTarget for Stepper {
+    fn target_name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Stepper {
+    /// Read back the actor type the port is configured as
+    pub async fn get_actor_type(&self) -> Result<ActorType, FtSwarmError> {
+        get_typed(self, RpcFunction::GetActorType).await
+    }
+
+    /// Set the speed in steps per second and the direction, the stepper moves while it is switched on
+    pub async fn set_speed(&self, speed: u32, direction: Direction) -> Result<(), FtSwarmError> {
+        self.run_command(RpcFunction::SetSpeed, vec![Argument::Int(direction.apply(speed))])
+            .await
+            .map(|_| ())
+    }
+
+    /// The speed in steps per second, negative when moving backward
+    pub async fn get_speed(&self) -> Result<i32, FtSwarmError> {
+        self.get_int(RpcFunction::GetSpeed).await
+    }
+
+    /// The current position in (micro) steps
+    pub async fn get_position(&self) -> Result<i32, FtSwarmError> {
+        self.get_int(RpcFunction::GetPosition).await
+    }
+
+    /// Define the current position, without moving
+    pub async fn set_position(&self, position: i64) -> Result<(), FtSwarmError> {
+        self.run_command(RpcFunction::SetPosition, vec![Argument::Int(position)])
+            .await
+            .map(|_| ())
+    }
+
+    /// Switch the stepper on, or stop it by coasting or braking. It is restored after a reconnect
+    pub async fn set_motion_type(&self, motion_type: MotionType) -> Result<(), FtSwarmError> {
+        run_recorded_command(self, RpcFunction::SetMotionType, vec![Argument::MotionType(motion_type)]).await
+    }
+
+    /// Whether the stepper is switched on, coasting or braking
+    pub async fn get_motion_type(&self) -> Result<MotionType, FtSwarmError> {
+        get_typed(self, RpcFunction::GetMotionType).await
+    }
+
+    /// Stop right away and hold the position
+    pub async fn brake(&self) -> Result<(), FtSwarmError> {
+        self.set_motion_type(MotionType::Brake).await
+    }
+
+    /// Move until `end_switch` is pressed, then brake and make that position 0
+    ///
+    /// `end_switch` yields the state of a digital input, starting with the current one, e.g.
+    /// [`Switch::changes`](crate::swarm_object::digital::Switch::changes).
+    /// Fails with [`FtSwarmError::HomingFailed`] if the switch isn't pressed within `timeout`.
+    pub async fn home(&self, end_switch: impl Stream<Item=ValueChange<bool>>, speed: u32, direction: Direction, timeout: Duration) -> Result<(), FtSwarmError> {
+        let mut end_switch = pin!(end_switch);
+
+        if !next_value(&mut end_switch).await? {
+            self.set_speed(speed, direction).await?;
+            self.set_motion_type(MotionType::On).await?;
+
+            let reached = tokio::time::timeout(timeout, async {
+                while !next_value(&mut end_switch).await? {}
+                Ok::<_, FtSwarmError>(())
+            });
+
+            match reached.await {
+                Ok(reached) => reached?,
+                Err(_) => {
+                    self.brake().await?;
+                    return Err(FtSwarmError::HomingFailed { object: self.name.clone() });
+                }
+            }
+        }
+
+        self.brake().await?;
+        self.set_position(0).await
+    }
+
+    async fn get_int(&self, function: RpcFunction) -> Result<i32, FtSwarmError> {
+        self.run_command(function, vec![])
+            .await
+            .and_then(|param| param.as_int().ok_or_else(|| FtSwarmError::unexpected("Invalid response")))
+    }
+}
+
+async fn next_value(changes: &mut (impl Stream<Item=ValueChange<bool>> + Unpin)) -> Result<bool, FtSwarmError> {
+    changes.next().await
+        .map(|change| change.value)
+        .ok_or(FtSwarmError::Disconnected)
+}
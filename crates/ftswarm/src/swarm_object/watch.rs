@@ -0,0 +1,53 @@
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio::time::Instant;
+use tokio_stream::Stream;
+use tokio_stream::wrappers::WatchStream;
+
+/// A value reported by the ftSwarm, and when it arrived
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueChange<T> {
+    pub value: T,
+    pub at: Instant,
+}
+
+/// The latest value of a subscribed object, shared by all clones of the object
+///
+/// Receivers don't borrow the object, so any number of tasks can await changes
+/// without holding the object's mutex.
+#[derive(Clone)]
+pub struct ValueWatch<T> {
+    sender: Arc<watch::Sender<ValueChange<T>>>,
+}
+
+impl<T: Clone + PartialEq + Send + Sync + 'static> ValueWatch<T> {
+    pub fn new(value: T) -> Self {
+        ValueWatch {
+            sender: Arc::new(watch::Sender::new(ValueChange { value, at: Instant::now() })),
+        }
+    }
+
+    /// Publish a new value. Receivers are only woken if it differs from the last one
+    pub fn update(&self, value: T) {
+        self.sender.send_if_modified(|change| {
+            if change.value == value {
+                return false;
+            }
+
+            *change = ValueChange { value, at: Instant::now() };
+            true
+        });
+    }
+
+    /// A receiver that sees the current value and is notified about every change
+    pub fn watch(&self) -> watch::Receiver<ValueChange<T>> {
+        self.sender.subscribe()
+    }
+
+    /// A stream that yields the current value, then every change
+    ///
+    /// If changes arrive faster than they are consumed, only the latest one is yielded
+    pub fn changes(&self) -> impl Stream<Item=ValueChange<T>> {
+        WatchStream::new(self.watch())
+    }
+}
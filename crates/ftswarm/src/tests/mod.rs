@@ -1,4 +1,14 @@
-use ftswarm_serial::FixedSerialPort;
+use ftswarm_proto::command::direct::FtSwarmDirectCommand;
+use ftswarm_proto::command::FtSwarmCommand;
+use ftswarm_proto::command::rpc::RpcFunction;
+use ftswarm_proto::error::ProtoError;
+use ftswarm_proto::Serialized;
+use ftswarm_serial::{AsyncLinePort, BlockingAdapter, SerialError};
+
+use std::sync::Arc;
+use tokio::io::DuplexStream;
+use tokio::time::Duration;
+use tokio_stream::StreamExt;
 
 use crate::prelude::*;
 
@@ -46,12 +56,15 @@ async fn test_servo() {
     let servo: Io<Servo> = Servo::create(&swarm, "example", ()).await;
     
     {
-        let servo = servo.lock().unwrap();
-        assert_eq!(servo.get_position().await, Ok(0));
-        assert_eq!(servo.get_offset().await, Ok(10));
+        let servo = lock(&servo).await.clone();
+        assert_eq!(servo.get_position().await.unwrap(), 0);
+        assert_eq!(servo.get_offset().await.unwrap(), 10);
 
         servo.set_offset(32).await.unwrap();
-        servo.set_position(32).await.unwrap_err();
+        match servo.set_position(32).await.unwrap_err() {
+            FtSwarmError::Firmware(error) => assert_eq!(error.message, "Port not found"),
+            other => panic!("Expected firmware error, got {:?}", other),
+        }
     }
 }
 
@@ -68,7 +81,1174 @@ async fn test_ntc() {
     let ntc: Io<Thermometer> = Thermometer::create(&swarm, "example", Hysteresis(0)).await;
     
     {
-        let ntc = ntc.lock().unwrap();
+        let ntc = lock(&ntc).await;
         assert_eq!(ntc.value, 0);
     }
-}
\ No newline at end of file
+}
+#[tokio::test]
+#[allow(deprecated)]
+async fn test_command_order() {
+    let static_serial = FixedSerialPort::new();
+    let swarm = FtSwarm::new(static_serial.clone());
+
+    for i in 0..5 {
+        swarm.send_command(FtSwarmCommand::Direct(FtSwarmDirectCommand::Custom(format!("cmd{}", i)))).await;
+    }
+    static_serial.add_response("R: Ok");
+    swarm.transact(FtSwarmCommand::Direct(FtSwarmDirectCommand::Custom("last".to_string()))).await.unwrap();
+
+    assert_eq!(static_serial.written_lines(), vec!["cmd0", "cmd1", "cmd2", "cmd3", "cmd4", "last"]);
+}
+
+#[tokio::test]
+async fn test_queued_request_order() {
+    let static_serial = FixedSerialPort::new();
+    static_serial.add_response("R: 0");
+    static_serial.add_response("R: 1");
+    static_serial.add_response("R: 2");
+
+    let swarm = FtSwarm::new(static_serial.clone());
+
+    // All three requests are queued before any of them is written
+    let (first, second, third) = tokio::join!(
+        swarm.transact(FtSwarmCommand::Direct(FtSwarmDirectCommand::Custom("first".to_string()))),
+        swarm.transact(FtSwarmCommand::Direct(FtSwarmDirectCommand::Custom("second".to_string()))),
+        swarm.transact(FtSwarmCommand::Direct(FtSwarmDirectCommand::Custom("third".to_string()))),
+    );
+
+    assert_eq!(static_serial.written_lines(), vec!["first", "second", "third"]);
+    assert_eq!(first.unwrap().as_int(), Some(0));
+    assert_eq!(second.unwrap().as_int(), Some(1));
+    assert_eq!(third.unwrap().as_int(), Some(2));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_parallel_transact() {
+    const TASKS: i32 = 32;
+
+    let static_serial = FixedSerialPort::new();
+    for i in 0..TASKS {
+        static_serial.add_response(&format!("R: {}", i));
+    }
+
+    let swarm = FtSwarm::new(static_serial.clone());
+    let controller = Controller::create(&swarm, "ftSwarm100", ()).await;
+    let controller = lock(&controller).await.clone();
+
+    let mut handles = Vec::new();
+    for register in 0..TASKS {
+        let controller = controller.clone();
+        handles.push(tokio::spawn(async move {
+            (register, controller.get_register(register as u8).await.unwrap())
+        }));
+    }
+
+    let mut results = Vec::new();
+    for handle in handles {
+        results.push(handle.await.unwrap());
+    }
+
+    // The n-th response belongs to the n-th written command
+    let written = static_serial.written_lines();
+    assert_eq!(written.len(), TASKS as usize);
+    for (register, response) in results {
+        assert_eq!(written[response as usize], format!("ftSwarm100.getRegister({})", register));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_parallel_clones() {
+    const TASKS: usize = 16;
+
+    let static_serial = FixedSerialPort::new();
+    for i in 0..TASKS {
+        static_serial.add_response(&format!("ftSwarm{}/host{}", i, i));
+    }
+
+    let swarm = FtSwarm::new(static_serial);
+    let mut handles = Vec::new();
+    for _ in 0..TASKS {
+        let swarm = swarm.clone();
+        handles.push(tokio::spawn(async move {
+            swarm.whoami().await.unwrap().serial.unwrap()
+        }));
+    }
+
+    let mut serials = Vec::new();
+    for handle in handles {
+        serials.push(handle.await.unwrap());
+    }
+
+    // Every response is delivered to exactly one caller
+    serials.sort();
+    assert_eq!(serials, (0..TASKS as i32).collect::<Vec<_>>());
+}
+
+fn custom_command(name: &str) -> FtSwarmCommand {
+    FtSwarmCommand::Direct(FtSwarmDirectCommand::Custom(name.to_string()))
+}
+
+#[tokio::test]
+async fn test_transact_timeout() {
+    let static_serial = FixedSerialPort::new();
+    let swarm = FtSwarm::new(static_serial).with_timeout(Duration::from_millis(50));
+
+    let result = swarm.transact(custom_command("first")).await;
+    assert!(matches!(result, Err(FtSwarmError::Timeout(timeout)) if timeout == Duration::from_millis(50)));
+
+    let result = swarm.transact_with_timeout(custom_command("second"), Duration::from_millis(20)).await;
+    assert!(matches!(result, Err(FtSwarmError::Timeout(timeout)) if timeout == Duration::from_millis(20)));
+}
+
+#[tokio::test]
+async fn test_late_reply_is_discarded() {
+    let static_serial = FixedSerialPort::new();
+    let swarm = FtSwarm::new(static_serial.clone());
+
+    let result = swarm.transact_with_timeout(custom_command("first"), Duration::from_millis(100)).await;
+    assert!(matches!(result, Err(FtSwarmError::Timeout(_))));
+
+    // The reply to the first command arrives after its caller gave up
+    static_serial.add_response("R: 1");
+    static_serial.add_response("R: 2");
+
+    let result = swarm.transact(custom_command("second")).await.unwrap();
+    assert_eq!(result.as_int(), Some(2));
+}
+
+#[tokio::test]
+async fn test_lost_reply_gets_back_in_step() {
+    use ftswarm_serial::fault::{Fault, FaultInjector, FaultSchedule};
+
+    let static_serial = FixedSerialPort::new();
+    let swarm = FtSwarm::new(FaultInjector::new(static_serial.clone(), FaultSchedule::seeded(0).at(0, Fault::Drop)));
+
+    // The reply to the first command is lost
+    static_serial.add_response("R: 1");
+    let result = swarm.transact_with_timeout(custom_command("first"), Duration::from_millis(50)).await;
+    assert!(matches!(result, Err(FtSwarmError::Timeout(_))));
+
+    // The next command waits until the reply is overdue and a marker was answered
+    static_serial.add_response("ftSwarm100/kelda");
+    static_serial.add_response("R: 2");
+    assert_eq!(swarm.transact(custom_command("second")).await.unwrap().as_int(), Some(2));
+    assert_eq!(static_serial.written_lines(), vec!["first", "whoami", "second"]);
+}
+
+#[tokio::test]
+async fn test_overdue_reply_is_dropped() {
+    use ftswarm_serial::fault::{Fault, FaultInjector, FaultSchedule};
+
+    let static_serial = FixedSerialPort::new();
+    let schedule = FaultSchedule::seeded(0).at(0, Fault::Delay(Duration::from_millis(300)));
+    let swarm = FtSwarm::new(FaultInjector::new(static_serial.clone(), schedule));
+
+    static_serial.add_response("R: 1");
+    static_serial.add_response("ftSwarm100/kelda");
+    static_serial.add_response("R: 2");
+
+    // The reply arrives after it was given up as lost, so it doesn't go to the next request
+    let result = swarm.transact_with_timeout(custom_command("first"), Duration::from_millis(50)).await;
+    assert!(matches!(result, Err(FtSwarmError::Timeout(_))));
+    assert_eq!(swarm.transact(custom_command("second")).await.unwrap().as_int(), Some(2));
+    assert_eq!(static_serial.written_lines(), vec!["first", "whoami", "second"]);
+}
+
+#[tokio::test]
+async fn test_injected_faults() {
+    use ftswarm_serial::fault::{Fault, FaultInjector, FaultSchedule};
+
+    let static_serial = FixedSerialPort::new();
+    let schedule = FaultSchedule::seeded(0)
+        .at(0, Fault::SpuriousLog)
+        .at(1, Fault::Drop)
+        .at(3, Fault::SpuriousError);
+    let port = FaultInjector::new(static_serial.clone(), schedule);
+    let log = port.log();
+    let swarm = FtSwarm::new(port);
+
+    // Log lines are skipped
+    static_serial.add_response("R: 1");
+    assert_eq!(swarm.transact(custom_command("first")).await.unwrap().as_int(), Some(1));
+
+    // A lost reply times out, and the replies get back in step before the next command is written
+    static_serial.add_response("R: 2");
+    let result = swarm.transact_with_timeout(custom_command("second"), Duration::from_millis(20)).await;
+    assert!(matches!(result, Err(FtSwarmError::Timeout(_))));
+    static_serial.add_response("ftSwarm100/kelda");
+    static_serial.add_response("R: 3");
+    assert_eq!(swarm.transact(custom_command("third")).await.unwrap().as_int(), Some(3));
+
+    // An error line nobody caused is dropped once it arrives
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    static_serial.add_response("R: 4");
+    assert_eq!(swarm.transact(custom_command("fourth")).await.unwrap().as_int(), Some(4));
+
+    assert_eq!(static_serial.written_lines(), vec!["first", "second", "whoami", "third", "fourth"]);
+    assert_eq!(log.entries(), vec![(0, Fault::SpuriousLog), (1, Fault::Drop), (3, Fault::SpuriousError)]);
+}
+
+#[tokio::test]
+async fn test_pipelined_requests() {
+    let (client, mut device) = pipe();
+    let (swarm, _) = tokio::join!(FtSwarm::new_async(client), boot(&mut device));
+    let swarm = swarm.unwrap();
+
+    let requests: Vec<_> = (0..10).map(|id| {
+        let swarm = swarm.clone();
+        tokio::spawn(async move { (id, swarm.transact(custom_command(&id.to_string())).await) })
+    }).collect();
+
+    // Eight commands are written before the first one is answered
+    let mut written = Vec::new();
+    for _ in 0..8 {
+        written.push(device.read_line().await.unwrap());
+    }
+    assert!(tokio::time::timeout(Duration::from_millis(50), device.read_line()).await.is_err());
+
+    for line in &written {
+        device.write_line(format!("R: {}", line)).await.unwrap();
+    }
+    for _ in 0..2 {
+        let line = device.read_line().await.unwrap();
+        device.write_line(format!("R: {}", line)).await.unwrap();
+    }
+
+    for request in requests {
+        let (id, reply) = request.await.unwrap();
+        assert_eq!(reply.unwrap().as_int(), Some(id));
+    }
+}
+
+/// Times out a `whoami` whose reply is late, and returns once the marker was written
+async fn lose_whoami(swarm: &FtSwarm, device: &mut Pipe) {
+    let (whoami, _) = tokio::join!(swarm.whoami(), async {
+        assert_eq!(device.read_line().await.unwrap(), "whoami");
+        assert_eq!(device.read_line().await.unwrap(), "whoami");
+    });
+    assert!(matches!(whoami, Err(FtSwarmError::Timeout(_))));
+}
+
+#[tokio::test]
+async fn test_late_whoami_is_not_the_marker() {
+    let (client, mut device) = pipe();
+    let (swarm, _) = tokio::join!(FtSwarm::new_async(client), boot(&mut device));
+    let swarm = swarm.unwrap().with_timeout(Duration::from_millis(50));
+
+    lose_whoami(&swarm, &mut device).await;
+    device.write_line("ftSwarm100/late".to_string()).await.unwrap();
+    device.write_line("ftSwarm100/kelda".to_string()).await.unwrap();
+
+    let (next, _) = tokio::join!(swarm.transact_with_timeout(custom_command("next"), Duration::from_secs(5)), async {
+        assert_eq!(device.read_line().await.unwrap(), "next");
+        device.write_line("R: 7".to_string()).await.unwrap();
+    });
+    assert_eq!(next.unwrap().as_int(), Some(7));
+}
+
+#[tokio::test]
+async fn test_lost_whoami_gets_back_in_step() {
+    let (client, mut device) = pipe();
+    let (swarm, _) = tokio::join!(FtSwarm::new_async(client), boot(&mut device));
+    let swarm = swarm.unwrap().with_timeout(Duration::from_millis(50));
+
+    // Only the marker is answered, which is told apart once nothing follows it
+    lose_whoami(&swarm, &mut device).await;
+    device.write_line("ftSwarm100/kelda".to_string()).await.unwrap();
+
+    let (next, _) = tokio::join!(swarm.transact_with_timeout(custom_command("next"), Duration::from_secs(5)), async {
+        assert_eq!(device.read_line().await.unwrap(), "next");
+        device.write_line("R: 7".to_string()).await.unwrap();
+    });
+    assert_eq!(next.unwrap().as_int(), Some(7));
+}
+
+/// Answers every command `<n>` with `R: <n>`, so a reply shows which request it belongs to
+#[derive(Default)]
+struct EchoPort {
+    replies: std::collections::VecDeque<String>,
+}
+
+impl SwarmSerialPort for EchoPort {
+    fn available(&self) -> Result<bool, SerialError> {
+        Ok(!self.replies.is_empty())
+    }
+
+    fn read_line(&mut self) -> Result<String, SerialError> {
+        self.replies.pop_front().ok_or(SerialError::Timeout)
+    }
+
+    fn write_line(&mut self, line: String) -> Result<(), SerialError> {
+        if line == "whoami" {
+            self.replies.push_back("ftSwarm100/kelda".to_string());
+        } else if line.parse::<i32>().is_ok() {
+            self.replies.push_back(format!("R: {}", line));
+        }
+        Ok(())
+    }
+
+    fn block_until(&mut self, _line: String) -> Result<(), SerialError> {
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_chaos_delay() {
+    use ftswarm_serial::fault::{Fault, FaultInjector, FaultSchedule};
+
+    // Besides the random delays, one reply is late enough to be given up as lost
+    let schedule = FaultSchedule::seeded(19)
+        .delay(0.3, Duration::from_millis(400))
+        .at(2, Fault::Delay(Duration::from_millis(400)));
+    let port = FaultInjector::new(EchoPort::default(), schedule);
+    let log = port.log();
+    let swarm = FtSwarm::new(port).with_timeout(Duration::from_millis(150));
+
+    let mut handles = Vec::new();
+    for task in 0..3 {
+        let swarm = swarm.clone();
+        handles.push(tokio::spawn(async move {
+            let mut answered = 0;
+            for request in 0..8 {
+                let id = 100 + task * 10 + request;
+                // A late reply may fail its own request, but never answers another one
+                if let Ok(reply) = swarm.transact(custom_command(&id.to_string())).await {
+                    assert_eq!(reply.as_int(), Some(id));
+                    answered += 1;
+                }
+            }
+            answered
+        }));
+    }
+
+    let mut answered = 0;
+    for handle in handles {
+        answered += handle.await.unwrap();
+    }
+
+    assert!(log.entries().len() > 1);
+    assert!(answered > 0);
+}
+
+#[tokio::test]
+async fn test_chaos_corrupt() {
+    use ftswarm_serial::fault::{FaultInjector, FaultSchedule};
+
+    let schedule = FaultSchedule::seeded(19).corrupt(0.3);
+    let port = FaultInjector::new(EchoPort::default(), schedule);
+    let log = port.log();
+    let swarm = FtSwarm::new(port).with_timeout(Duration::from_millis(150));
+
+    let mut replies = Vec::new();
+    for id in 100..124 {
+        replies.push((id, swarm.transact(custom_command(&id.to_string())).await.unwrap()));
+    }
+
+    // The n-th line read is the reply to the n-th request, a garbled reply still reaches its own request
+    let corrupted: Vec<usize> = log.entries().into_iter().map(|(line, _)| line).collect();
+    assert!(!corrupted.is_empty());
+    for (line, (id, reply)) in replies.into_iter().enumerate() {
+        let expected = if corrupted.contains(&line) { None } else { Some(id) };
+        assert_eq!(reply.as_int(), expected);
+    }
+}
+
+#[tokio::test]
+async fn test_cancelled_request_is_not_sent() {
+    let static_serial = FixedSerialPort::new();
+    let swarm = FtSwarm::new(static_serial.clone());
+
+    // Polled once, so the command is queued, then dropped before it is written
+    {
+        let cancelled = swarm.transact(custom_command("cancelled"));
+        tokio::pin!(cancelled);
+        tokio::select! {
+            biased;
+            _ = &mut cancelled => panic!("Request finished without a response"),
+            _ = std::future::ready(()) => {}
+        }
+    }
+
+    static_serial.add_response("R: Ok");
+    swarm.transact(custom_command("next")).await.unwrap();
+
+    assert_eq!(static_serial.written_lines(), vec!["next"]);
+}
+
+#[tokio::test]
+async fn test_error_sources() {
+    use std::error::Error;
+
+    let static_serial = FixedSerialPort::new();
+    static_serial.add_response("not a whoami response");
+
+    let swarm = FtSwarm::new(static_serial);
+    let error = swarm.whoami().await.unwrap_err();
+    assert!(matches!(error, FtSwarmError::Protocol(ProtoError::UnexpectedResponse(_))));
+    assert!(error.source().is_some());
+}
+
+#[tokio::test]
+async fn test_failed_create() {
+    let static_serial = FixedSerialPort::new();
+    static_serial.add_response("                ^ Error: Port not found");
+
+    let swarm = FtSwarm::new(static_serial);
+    let error = match Switch::try_create(&swarm, "A9", NormallyOpen::Open).await {
+        Ok(_) => panic!("Expected setup to fail"),
+        Err(error) => error,
+    };
+
+    match error {
+        FtSwarmError::Setup { object, step, source } => {
+            assert_eq!(object, "A9");
+            assert_eq!(step, RpcFunction::SetSensorType);
+            assert!(matches!(*source, FtSwarmError::Firmware(_)));
+        }
+        other => panic!("Expected setup error, got {:?}", other),
+    }
+
+    assert!(crate::lock(&swarm.inner).await.objects.is_empty());
+}
+
+#[tokio::test]
+async fn test_try_create() {
+    let static_serial = FixedSerialPort::new();
+    static_serial.add_response("R: Ok");
+    static_serial.add_response("R: 1");
+
+    let swarm = FtSwarm::new(static_serial);
+    let switch = Switch::try_create(&swarm, "A1", NormallyOpen::Open).await.unwrap();
+    assert!(lock(&switch).await.value);
+    assert!(crate::lock(&swarm.inner).await.objects.contains_key("A1"));
+}
+
+/// An object whose second setup step is a command that fails
+#[derive(Clone)]
+struct FailingSetup {
+    name: String,
+    swarm: FtSwarm,
+}
+
+impl crate::swarm_object::Updateable for FailingSetup {
+    fn handle_subscription(&mut self, _message: &ftswarm_proto::message_parser::rpc::RPCReturnParam) {}
+}
+
+impl NewSwarmObject<()> for FailingSetup {
+    fn new(name: &str, swarm: FtSwarm, _params: ()) -> Box<Self> {
+        Box::new(FailingSetup { name: name.to_string(), swarm })
+    }
+
+    async fn init(&mut self) -> Result<(), FtSwarmError> {
+        self.run_setup_command(RpcFunction::SetSensorType, vec![ftswarm_proto::command::argument::Argument::Int(2)]).await?;
+        self.run_setup_command(RpcFunction::SetRegister, vec![]).await?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn swarm(&self) -> &FtSwarm {
+        &self.swarm
+    }
+}
+
+impl SwarmObject<()> for FailingSetup {}
+
+#[tokio::test]
+async fn test_failed_create_keeps_setup() {
+    let static_serial = FixedSerialPort::new();
+    static_serial.add_response("R: Ok");
+    static_serial.add_response("R: 1");
+    static_serial.add_response("R: Ok");
+    static_serial.add_response("                ^ Error: Invalid argument");
+
+    let swarm = FtSwarm::new(static_serial);
+    Switch::try_create(&swarm, "A1", NormallyOpen::Open).await.unwrap();
+    let setup = || async { swarm.setup_of("A1").await.iter().map(Serialized::serialize).collect::<Vec<_>>() };
+    assert_eq!(setup().await, vec!["A1.setSensorType(2, 0)", "A1.subscribe(0)"]);
+
+    // The failed object of the same name doesn't replace the setup of the switch
+    assert!(FailingSetup::try_create(&swarm, "A1", ()).await.is_err());
+    assert_eq!(setup().await, vec!["A1.setSensorType(2, 0)", "A1.subscribe(0)"]);
+}
+
+#[tokio::test]
+async fn test_async_port() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut server = AsyncLinePort::new(server);
+
+    // A minimal ftSwarm on the other end of the pipe
+    let device = tokio::spawn(async move {
+        assert_eq!(server.read_line().await.unwrap(), "startCLI");
+        server.write_line("boot noise".to_string()).await.unwrap();
+        server.write_line("@@@ ftSwarmOS CLI started".to_string()).await.unwrap();
+        assert_eq!(server.read_line().await.unwrap(), "whoami");
+        server.write_line("R: ftSwarm100/example".to_string()).await.unwrap();
+    });
+
+    let swarm = FtSwarm::new_async(AsyncLinePort::new(client)).await.unwrap();
+    let whoami = swarm.whoami().await.unwrap();
+    assert_eq!(whoami.hostname, "example");
+    device.await.unwrap();
+
+    // The device hung up, so pending and new requests fail instead of hanging
+    assert!(matches!(swarm.uptime().await, Err(FtSwarmError::Disconnected)));
+}
+
+#[tokio::test]
+async fn test_blocking_adapter() {
+    let static_serial = FixedSerialPort::new();
+    static_serial.add_response("ftSwarm100/example");
+
+    let swarm = FtSwarm::new_async(BlockingAdapter::new(static_serial.clone())).await.unwrap();
+    assert_eq!(swarm.whoami().await.unwrap().id, "ftSwarm100");
+    assert_eq!(static_serial.written_lines(), vec!["whoami".to_string()]);
+}
+
+/// Answers `whoami`, but times out once in the middle of every line, like a blocking port whose
+/// read timeout is shorter than the gap between two parts of a line
+#[derive(Default)]
+struct SplitLinePort {
+    lines: std::collections::VecDeque<String>,
+    started: bool,
+}
+
+impl SwarmSerialPort for SplitLinePort {
+    fn available(&self) -> Result<bool, SerialError> {
+        Ok(!self.lines.is_empty())
+    }
+
+    fn read_line(&mut self) -> Result<String, SerialError> {
+        self.started = !self.started;
+        if self.started {
+            return Err(SerialError::Timeout);
+        }
+        self.lines.pop_front().ok_or(SerialError::Timeout)
+    }
+
+    fn write_line(&mut self, line: String) -> Result<(), SerialError> {
+        if line == "whoami" {
+            self.lines.push_back("R: ftSwarm100/example".to_string());
+        }
+        Ok(())
+    }
+
+    fn block_until(&mut self, _line: String) -> Result<(), SerialError> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_blocking_adapter_partial_line() {
+    // A timeout in the middle of a line doesn't end the connection
+    let swarm = FtSwarm::new_async(BlockingAdapter::new(SplitLinePort::default())).await.unwrap();
+    assert_eq!(swarm.whoami().await.unwrap().hostname, "example");
+    assert_eq!(*swarm.connection_state().borrow(), ConnectionState::Connected);
+}
+
+type Pipe = AsyncLinePort<DuplexStream>;
+
+/// Two connected ports, the second one plays the ftSwarm
+fn pipe() -> (Pipe, Pipe) {
+    let (client, device) = tokio::io::duplex(1024);
+    (AsyncLinePort::new(client), AsyncLinePort::new(device))
+}
+
+#[tokio::test]
+async fn test_tcp_port() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    // A stand-in for the CLI of an ftSwarm, which sends a reply in two parts
+    let device = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut read_line = || {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            line
+        };
+
+        assert_eq!(read_line(), "startCLI\r\n");
+        writer.write_all(b"boot noise\r\n@@@ ftSwarmOS CLI started\r\n").unwrap();
+        assert_eq!(read_line(), "whoami\r\n");
+        writer.write_all(b"R: ftSwarm100/").unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        writer.write_all(b"example\r\n").unwrap();
+        // The rest of the line takes longer than a blocking read would wait
+        assert_eq!(read_line(), "whoami\r\n");
+        writer.write_all(b"R: ftSwarm101/").unwrap();
+        std::thread::sleep(Duration::from_millis(600));
+        writer.write_all(b"slow\r\n").unwrap();
+        assert_eq!(read_line(), "uptime\r\n");
+    });
+
+    let swarm = FtSwarm::new_async(TcpCommunication::connect(address).await.unwrap()).await.unwrap();
+    assert_eq!(swarm.whoami().await.unwrap().hostname, "example");
+    assert_eq!(swarm.whoami().await.unwrap().hostname, "slow");
+
+    // The connection is closed without a reply
+    let uptime = swarm.uptime().await;
+    device.join().unwrap();
+    assert!(uptime.is_err());
+}
+
+#[tokio::test]
+async fn test_blocking_tcp_port() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let device = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut read_line = || {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            line
+        };
+
+        assert_eq!(read_line(), "startCLI\r\n");
+        writer.write_all(b"boot noise\r\n@@@ ftSwarmOS CLI started\r\n").unwrap();
+        assert_eq!(read_line(), "whoami\r\n");
+        writer.write_all(b"R: ftSwarm100/").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        writer.write_all(b"example\r\n").unwrap();
+    });
+
+    let swarm = FtSwarm::new(TcpCommunication::connect(address).await.unwrap());
+    assert_eq!(swarm.whoami().await.unwrap().hostname, "example");
+    device.join().unwrap();
+}
+
+#[tokio::test]
+async fn test_tcp_connect_refused() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    drop(listener);
+
+    let result = TcpCommunication::connect_timeout(address, Duration::from_millis(500)).await;
+    assert!(matches!(result, Err(SerialError::IoError(_))));
+}
+
+#[tokio::test]
+async fn test_cluster() {
+    use crate::cluster::SwarmCluster;
+
+    let kelda = FixedSerialPort::new();
+    let lisa = FixedSerialPort::new();
+    kelda.add_response("R: ftSwarm100/kelda");
+    lisa.add_response("R: ftSwarm101/lisa");
+
+    let mut cluster = SwarmCluster::new();
+    cluster.add(FtSwarm::new(kelda.clone())).await.unwrap();
+    assert_eq!(cluster.add(FtSwarm::new(lisa.clone())).await.unwrap().serial, Some(101));
+    assert!(cluster.get("ftSwarm100").is_some());
+    assert!(cluster.by_serial(101).is_some());
+
+    // A second kelda is turned away, and left running
+    let twin = FixedSerialPort::new();
+    twin.add_response("R: ftSwarm102/kelda");
+    let added = cluster.add(FtSwarm::new(twin.clone())).await;
+    assert!(matches!(added, Err(FtSwarmError::InvalidArgument(_))));
+    assert_eq!(twin.written_lines(), vec!["whoami"]);
+    assert_eq!(cluster.members().count(), 2);
+
+    // Created on the controller the name points at, without the hostname
+    lisa.add_response("R: Ok");
+    lisa.add_response("R: 1");
+    let switch = cluster.create::<Switch, _>("LISA.A1", NormallyOpen::Open).await.unwrap();
+    assert!(lock(&switch).await.value);
+    assert_eq!(lisa.written_lines().last().unwrap(), "A1.getValue()");
+    assert_eq!(kelda.written_lines(), vec!["whoami"]);
+
+    let unknown = cluster.create::<Switch, _>("otto.A1", NormallyOpen::Open).await;
+    assert!(matches!(unknown, Err(FtSwarmError::NotFound(_))));
+    let unqualified = cluster.create::<Switch, _>("A1", NormallyOpen::Open).await;
+    assert!(matches!(unqualified, Err(FtSwarmError::InvalidArgument(_))));
+
+    kelda.add_response("R: ftSwarm100/kelda");
+    lisa.add_response("R: ftSwarm101/lisa");
+    let whoami = cluster.whoami().await;
+    assert_eq!(whoami.iter().map(|(hostname, _)| hostname.as_str()).collect::<Vec<_>>(), vec!["kelda", "lisa"]);
+    assert!(whoami.iter().all(|(hostname, answer)| answer.as_ref().unwrap().hostname == *hostname));
+
+    assert!(cluster.is_healthy());
+    assert_eq!(cluster.health(), vec![
+        ("kelda".to_string(), ConnectionState::Connected),
+        ("lisa".to_string(), ConnectionState::Connected),
+    ]);
+
+    cluster.shutdown().await;
+    assert_eq!(kelda.written_lines().last().unwrap(), "halt");
+    assert_eq!(lisa.written_lines().last().unwrap(), "halt");
+    assert!(!cluster.is_healthy());
+}
+
+const MODEL_TOML: &str = r#"
+[controller]
+port = "/dev/ttyUSB0"
+
+[objects.lift]
+kind = "Switch"
+port = "A1"
+normally_open = false
+
+[objects.oven]
+kind = "Thermometer"
+port = "A2"
+hysteresis = 5
+
+[objects.gate]
+kind = "Servo"
+port = "SERVO1"
+offset = -10
+"#;
+
+#[test]
+fn test_model_config() {
+    use crate::config::ObjectKind;
+
+    let config = ModelConfig::from_toml(MODEL_TOML).unwrap();
+    assert_eq!(config.controller.port.as_deref(), Some("/dev/ttyUSB0"));
+    assert_eq!(config.objects["oven"].kind, ObjectKind::Thermometer);
+    assert_eq!(config.objects["oven"].hysteresis, Some(5));
+
+    let yaml = ModelConfig::from_yaml(r#"
+controller:
+  port: /dev/ttyUSB0
+objects:
+  lift: { kind: Switch, port: A1, normally_open: false }
+  oven: { kind: Thermometer, port: A2, hysteresis: 5 }
+  gate: { kind: Servo, port: SERVO1, offset: -10 }
+"#).unwrap();
+    assert_eq!(yaml, config);
+
+    let invalid = [
+        // The option doesn't fit the kind
+        "[controller]\nport = \"x\"\n[objects.lift]\nkind = \"Switch\"\nport = \"A1\"\nhysteresis = 5",
+        // Two objects on one port
+        "[controller]\nport = \"x\"\n[objects.a]\nkind = \"Lamp\"\nport = \"M1\"\n[objects.b]\nkind = \"Motor\"\nport = \"m1\"",
+        // Two ways to reach the controller
+        "[controller]\nport = \"x\"\nhostname = \"kelda\"",
+        "[controller]\nport = \"x\"\n[objects.lift]\nkind = \"Catapult\"\nport = \"A1\"",
+        "[controller]\nport = \"x\"\n[objects.lift]\nkind = \"Switch\"\nport = \"A1\"\ninverted = true",
+    ];
+    for text in invalid {
+        assert!(matches!(ModelConfig::from_toml(text), Err(FtSwarmError::Config(_))), "{}", text);
+    }
+
+    let unknown_format = ModelConfig::from_file("Cargo.toml.json");
+    assert!(matches!(unknown_format, Err(FtSwarmError::Config(_))));
+}
+
+#[tokio::test]
+async fn test_model() {
+    let static_serial = FixedSerialPort::new();
+    // Created in the order of the aliases: gate, lift, oven
+    static_serial.add_response("R: Ok");
+    static_serial.add_response("R: Ok");
+    static_serial.add_response("R: 1");
+    static_serial.add_response("R: Ok");
+    static_serial.add_response("R: 21");
+
+    let config = ModelConfig::from_toml(MODEL_TOML).unwrap();
+    let model = Model::build(FtSwarm::new(static_serial.clone()), &config).await.unwrap();
+
+    // The responses were queued up front, so the last one can arrive before its command is written
+    assert_eq!(wait_for_lines(&static_serial, 7).await, vec![
+        "SERVO1.setOffset(-10)",
+        "A1.setSensorType(2, 1)", "A1.subscribe(0)", "A1.getValue()",
+        "A2.setSensorType(7, 0)", "A2.subscribe(5)", "A2.getValue()",
+    ]);
+
+    assert!(lock(&model.get::<Switch>("lift").unwrap()).await.value);
+    assert_eq!(lock(&model.get::<Thermometer>("oven").unwrap()).await.hysteresis.0, 5);
+    assert!(model.get::<Servo>("gate").is_ok());
+
+    let mismatch = model.get::<Motor>("lift");
+    assert!(matches!(mismatch, Err(FtSwarmError::InvalidArgument(message)) if message == "lift is a Switch, not a Motor"));
+    assert!(matches!(model.get::<Switch>("crane"), Err(FtSwarmError::NotFound(_))));
+
+    let mut aliases = model.aliases().collect::<Vec<_>>();
+    aliases.sort();
+    assert_eq!(aliases, vec!["gate", "lift", "oven"]);
+}
+
+/// Wait until `port` was sent `count` lines
+async fn wait_for_lines(port: &FixedSerialPort, count: usize) -> Vec<String> {
+    for _ in 0..100 {
+        let lines = port.written_lines();
+        if lines.len() >= count {
+            return lines;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("Expected {} lines, got {:?}", count, port.written_lines());
+}
+
+#[tokio::test]
+async fn test_halt_on_drop() {
+    let static_serial = FixedSerialPort::new();
+    let swarm = FtSwarm::new(static_serial.clone());
+    let clone = swarm.clone();
+
+    // Only the handle that opened the connection closes it
+    drop(clone);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(static_serial.written_lines().is_empty());
+
+    drop(swarm);
+    assert_eq!(static_serial.written_lines(), vec!["halt"]);
+}
+
+#[test]
+fn test_halt_on_drop_at_runtime_exit() {
+    let current_thread = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let static_serial = FixedSerialPort::new();
+    current_thread.block_on(async {
+        let swarm = FtSwarm::new(static_serial.clone());
+        swarm.on_shutdown(|swarm| async move { swarm.queue_command(custom_command("park")).await });
+        drop(swarm);
+    });
+    drop(current_thread);
+    // The actions can't run while the only thread is blocked in drop
+    assert_eq!(static_serial.written_lines(), vec!["halt"]);
+
+    let multi_thread = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+    let static_serial = FixedSerialPort::new();
+    multi_thread.block_on(async {
+        let swarm = FtSwarm::new(static_serial.clone());
+        swarm.on_shutdown(|swarm| async move { swarm.queue_command(custom_command("park")).await });
+        drop(swarm);
+    });
+    drop(multi_thread);
+    assert_eq!(static_serial.written_lines(), vec!["park", "halt"]);
+}
+
+#[tokio::test]
+async fn test_shutdown_actions() {
+    let static_serial = FixedSerialPort::new();
+    let swarm = FtSwarm::new(static_serial.clone()).with_shutdown_deadline(Duration::from_millis(100));
+
+    swarm.on_shutdown(|swarm| async move { swarm.queue_command(custom_command("park")).await });
+    // Cut short by the deadline
+    swarm.on_shutdown(|_| tokio::time::sleep(Duration::from_secs(10)));
+
+    swarm.shutdown().await;
+    assert_eq!(static_serial.written_lines(), vec!["park", "halt"]);
+    assert_eq!(*swarm.connection_state().borrow(), ConnectionState::Closed);
+    assert!(matches!(swarm.whoami().await, Err(FtSwarmError::Disconnected)));
+
+    // The actions only run once
+    swarm.shutdown().await;
+    drop(swarm);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(static_serial.written_lines(), vec!["park", "halt"]);
+}
+
+#[tokio::test]
+async fn test_watchdog() {
+    let static_serial = FixedSerialPort::new();
+    let swarm = FtSwarm::new(static_serial.clone());
+    let watchdog = swarm.watchdog(Duration::from_millis(50));
+
+    // Halted once per silence
+    assert_eq!(wait_for_lines(&static_serial, 1).await, vec!["halt"]);
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(static_serial.written_lines().len(), 1);
+
+    // Feeding counts as activity
+    for _ in 0..10 {
+        watchdog.feed();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(static_serial.written_lines().len(), 1);
+
+    swarm.queue_command(custom_command("ping")).await;
+    assert_eq!(wait_for_lines(&static_serial, 3).await, vec!["halt", "ping", "halt"]);
+}
+
+async fn boot(device: &mut Pipe) {
+    assert_eq!(device.read_line().await.unwrap(), "startCLI");
+    device.write_line("@@@ ftSwarmOS CLI started".to_string()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reconnect() {
+    let (plug, connections) = tokio::sync::mpsc::unbounded_channel::<Pipe>();
+    let connections = Arc::new(tokio::sync::Mutex::new(connections));
+    let policy = ReconnectPolicy {
+        initial_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(1),
+        max_attempts: Some(3),
+    };
+
+    let (client, mut device) = pipe();
+    plug.send(client).unwrap();
+    let connect = move || {
+        let connections = connections.clone();
+        async move { connections.lock().await.recv().await.ok_or(SerialError::ManualDisconnect) }
+    };
+    let (swarm, _) = tokio::join!(FtSwarm::connect_with_reconnect(connect, policy), boot(&mut device));
+    let swarm = swarm.unwrap();
+    let mut state = swarm.connection_state();
+    assert_eq!(*state.borrow(), ConnectionState::Connected);
+
+    let (_switch, setup) = tokio::join!(Switch::create(&swarm, "A1", NormallyOpen::Open), async {
+        let set_sensor_type = device.read_line().await.unwrap();
+        device.write_line("R: Ok".to_string()).await.unwrap();
+        let subscribe = device.read_line().await.unwrap();
+        assert_eq!(device.read_line().await.unwrap(), "A1.getValue()");
+        device.write_line("R: 0".to_string()).await.unwrap();
+        vec![set_sensor_type, subscribe]
+    });
+
+    // Unplug, requests fail right away while the connection is down
+    drop(device);
+    state.wait_for(|state| matches!(state, ConnectionState::Reconnecting { .. })).await.unwrap();
+    assert!(matches!(swarm.whoami().await, Err(FtSwarmError::Disconnected)));
+
+    // Plug in again, the switch is set up again before requests are sent
+    let (client, mut device) = pipe();
+    plug.send(client).unwrap();
+    boot(&mut device).await;
+    assert_eq!(device.read_line().await.unwrap(), setup[0]);
+    device.write_line("R: Ok".to_string()).await.unwrap();
+    assert_eq!(device.read_line().await.unwrap(), setup[1]);
+    state.wait_for(|state| *state == ConnectionState::Connected).await.unwrap();
+
+    let (whoami, _) = tokio::join!(swarm.whoami(), async {
+        assert_eq!(device.read_line().await.unwrap(), "whoami");
+        device.write_line("R: ftSwarm100/example".to_string()).await.unwrap();
+    });
+    assert_eq!(whoami.unwrap().hostname, "example");
+
+    // Unplug for good, the policy gives up after three attempts
+    drop(plug);
+    drop(device);
+    state.wait_for(|state| *state == ConnectionState::Lost).await.unwrap();
+    assert!(matches!(swarm.uptime().await, Err(FtSwarmError::Disconnected)));
+}
+
+#[test]
+fn test_reconnect_policy() {
+    let policy = ReconnectPolicy {
+        initial_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(500),
+        max_attempts: None,
+    };
+
+    assert_eq!(policy.delay(1), Duration::from_millis(100));
+    assert_eq!(policy.delay(2), Duration::from_millis(200));
+    assert_eq!(policy.delay(3), Duration::from_millis(400));
+    assert_eq!(policy.delay(4), Duration::from_millis(500));
+    assert_eq!(policy.delay(100), Duration::from_millis(500));
+}
+
+#[tokio::test]
+async fn test_value_changes() {
+    let (client, mut device) = pipe();
+    let (swarm, _) = tokio::join!(FtSwarm::new_async(client), boot(&mut device));
+    let swarm = swarm.unwrap();
+
+    let (switch, _) = tokio::join!(Switch::create(&swarm, "A1", NormallyOpen::Open), async {
+        device.read_line().await.unwrap();
+        device.write_line("R: Ok".to_string()).await.unwrap();
+        device.read_line().await.unwrap();
+        device.read_line().await.unwrap();
+        device.write_line("R: 0".to_string()).await.unwrap();
+    });
+
+    // Several consumers follow the switch, none of them holds its lock
+    let (mut watch, mut changes) = {
+        let switch = lock(&switch).await;
+        (switch.watch(), switch.changes())
+    };
+    let initial = changes.next().await.unwrap();
+    assert!(!initial.value);
+
+    device.write_line("S: A1 1".to_string()).await.unwrap();
+    watch.changed().await.unwrap();
+    assert!(watch.borrow_and_update().value);
+
+    let change = changes.next().await.unwrap();
+    assert!(change.value);
+    assert!(change.at >= initial.at);
+
+    // Repeated values don't wake anyone
+    device.write_line("S: A1 1".to_string()).await.unwrap();
+    device.write_line("S: A1 0".to_string()).await.unwrap();
+    watch.changed().await.unwrap();
+    assert!(!watch.borrow_and_update().value);
+    assert!(!changes.next().await.unwrap().value);
+    assert!(!lock(&switch).await.value);
+}
+
+#[test]
+fn test_joystick_position() {
+    use ftswarm_proto::message_parser::rpc::RPCReturnParam;
+
+    let parse = |payload: &str| JoystickPosition::try_from(&RPCReturnParam::from(payload.to_string()));
+    assert_eq!(parse("-12 40").unwrap(), JoystickPosition::new(-12, 40));
+    assert_eq!(parse("3,-4").unwrap(), JoystickPosition::new(3, -4));
+    assert!(matches!(parse("7"), Err(FtSwarmError::Protocol(_))));
+    assert!(matches!(parse("1 2 3"), Err(FtSwarmError::Protocol(_))));
+}
+
+/// Answers the commands of a homing stepper `M1`. The end switch `A1` is pressed once the
+/// stepper is switched on, unless `reachable` is false. Returns the commands up to the last one
+async fn serve_homing(device: &mut Pipe, reachable: bool) -> Vec<String> {
+    let mut commands = Vec::new();
+    loop {
+        let command = device.read_line().await.unwrap();
+        device.write_line("R: Ok".to_string()).await.unwrap();
+
+        if command == "M1.setMotionType(2)" && reachable {
+            device.write_line("S: A1 1".to_string()).await.unwrap();
+        }
+
+        let done = command == "M1.setPosition(0)" || (command == "M1.setMotionType(1)" && !reachable);
+        commands.push(command);
+        if done {
+            return commands;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_stepper_homing() {
+    let (client, mut device) = pipe();
+    let (swarm, _) = tokio::join!(FtSwarm::new_async(client), boot(&mut device));
+    let swarm = swarm.unwrap();
+
+    let (switch, _) = tokio::join!(Switch::create(&swarm, "A1", NormallyOpen::Open), async {
+        device.read_line().await.unwrap();
+        device.write_line("R: Ok".to_string()).await.unwrap();
+        device.read_line().await.unwrap();
+        device.read_line().await.unwrap();
+        device.write_line("R: 0".to_string()).await.unwrap();
+    });
+    let (stepper, _) = tokio::join!(Stepper::create(&swarm, "M1", ()), async {
+        assert_eq!(device.read_line().await.unwrap(), "M1.setActorType(8)");
+        device.write_line("R: Ok".to_string()).await.unwrap();
+    });
+    let stepper = lock(&stepper).await.clone();
+    let switch = lock(&switch).await.clone();
+
+    let (homed, commands) = tokio::join!(
+        stepper.home(switch.changes(), 100, Direction::Backward, Duration::from_secs(5)),
+        serve_homing(&mut device, true),
+    );
+    homed.unwrap();
+    assert_eq!(commands, vec![
+        "M1.setSpeed(-100)",
+        "M1.setMotionType(2)",
+        "M1.setMotionType(1)",
+        "M1.setPosition(0)",
+    ]);
+
+    // The switch is still pressed, so the stepper is already home
+    let (homed, commands) = tokio::join!(
+        stepper.home(switch.changes(), 100, Direction::Backward, Duration::from_secs(5)),
+        serve_homing(&mut device, true),
+    );
+    homed.unwrap();
+    assert_eq!(commands, vec!["M1.setMotionType(1)", "M1.setPosition(0)"]);
+
+    device.write_line("S: A1 0".to_string()).await.unwrap();
+    switch.watch().wait_for(|change| !change.value).await.unwrap();
+
+    let (homed, commands) = tokio::join!(
+        stepper.home(switch.changes(), 100, Direction::Forward, Duration::from_millis(50)),
+        serve_homing(&mut device, false),
+    );
+    assert!(matches!(homed, Err(FtSwarmError::HomingFailed { object }) if object == "M1"));
+    assert_eq!(commands, vec!["M1.setSpeed(100)", "M1.setMotionType(2)", "M1.setMotionType(1)"]);
+}
+
+#[tokio::test]
+async fn test_on_trigger() {
+    let static_serial = FixedSerialPort::new();
+    for response in ["R: Ok", "R: 0", "R: Ok", "R: Ok", "R: Ok", "R: Ok"] {
+        static_serial.add_response(response);
+    }
+
+    let swarm = FtSwarm::new(static_serial.clone());
+    let switch = Switch::create(&swarm, "A1", NormallyOpen::Open).await;
+    let motor = Motor::create(&swarm, "M1", ()).await;
+
+    let switch = lock(&switch).await.clone();
+    let motor = lock(&motor).await.clone();
+    switch.on_trigger(TriggerEvent::Up, &motor, 0).await.unwrap();
+    switch.on_trigger_forward(TriggerEvent::Down, &motor).await.unwrap();
+    switch.on_trigger(TriggerEvent::Up, &motor, 255).await.unwrap();
+
+    let written = static_serial.written_lines();
+    assert_eq!(&written[written.len() - 3..], [
+        "A1.onTrigger(0, M1, 0)",
+        "A1.onTrigger(1, M1)",
+        "A1.onTrigger(0, M1, 255)",
+    ]);
+
+    // Each event keeps its own trigger for a reconnect, the latest one wins
+    let triggers: Vec<String> = crate::lock(&swarm.inner).await.setup.iter()
+        .filter(|command| command.function == RpcFunction::OnTrigger)
+        .map(|command| command.serialize())
+        .collect();
+    assert_eq!(triggers, ["A1.onTrigger(1, M1)", "A1.onTrigger(0, M1, 255)"]);
+}
+
+#[tokio::test]
+async fn test_motion_type_is_restored() {
+    let static_serial = FixedSerialPort::new();
+    for response in ["R: Ok", "R: Ok", "R: Ok"] {
+        static_serial.add_response(response);
+    }
+
+    let swarm = FtSwarm::new(static_serial.clone());
+    let motor = Motor::create(&swarm, "M1", ()).await;
+    let motor = lock(&motor).await.clone();
+    motor.set_motion_type(ftswarm_proto::command::enums::MotionType::On).await.unwrap();
+    motor.brake().await.unwrap();
+
+    // Only the latest motion type is sent again after a reconnect
+    let setup: Vec<String> = crate::lock(&swarm.inner).await.setup.iter()
+        .map(|command| command.serialize())
+        .collect();
+    assert_eq!(setup, ["M1.setActorType(0)", "M1.setMotionType(1)"]);
+}
+
+#[tokio::test]
+async fn test_user_events() {
+    let (client, mut device) = pipe();
+    let (swarm, _) = tokio::join!(FtSwarm::new_async(client), boot(&mut device));
+    let swarm = swarm.unwrap();
+    let mut events = swarm.user_events();
+    let mut other_events = swarm.clone().user_events();
+
+    let controller = Controller::create(&swarm, "ftSwarm100", ()).await;
+    let controller = lock(&controller).await.clone();
+
+    let (sent, _) = tokio::join!(controller.trigger_user_event(&[1, -2, 3]), async {
+        assert_eq!(device.read_line().await.unwrap(), "ftSwarm100.triggerUserEvent(1, -2, 3)");
+        device.write_line("U: 4 5".to_string()).await.unwrap();
+        device.write_line("R: Ok".to_string()).await.unwrap();
+    });
+    sent.unwrap();
+
+    assert_eq!(events.recv().await.unwrap(), UserEvent { params: vec![4, 5] });
+    assert_eq!(other_events.recv().await.unwrap().params, vec![4, 5]);
+
+    assert!(matches!(controller.trigger_user_event(&[0; 11]).await, Err(FtSwarmError::InvalidArgument(_))));
+    assert!(matches!(controller.trigger_user_event(&[i64::MAX]).await, Err(FtSwarmError::InvalidArgument(_))));
+}
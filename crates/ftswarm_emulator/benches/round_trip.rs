@@ -0,0 +1,57 @@
+//! Measures request latency and throughput of `FtSwarm` against the emulator.
+//!
+//! The emulator answers instantly, so the numbers show the overhead of the I/O loop itself.
+//! Run with `cargo bench -p ftswarm_emulator`.
+
+use std::time::{Duration, Instant};
+use ftswarm::prelude::*;
+use ftswarm_emulator::EmulatedSerialPort;
+
+const SEQUENTIAL_REQUESTS: u32 = 500;
+const PARALLEL_TASKS: u32 = 8;
+const REQUESTS_PER_TASK: u32 = 250;
+
+async fn sequential(controller: &Controller) -> Duration {
+    let start = Instant::now();
+    for _ in 0..SEQUENTIAL_REQUESTS {
+        controller.get_register(0).await.unwrap();
+    }
+    start.elapsed()
+}
+
+async fn parallel(controller: &Controller) -> Duration {
+    let start = Instant::now();
+    let mut handles = Vec::new();
+    for _ in 0..PARALLEL_TASKS {
+        let controller = controller.clone();
+        handles.push(tokio::spawn(async move {
+            for _ in 0..REQUESTS_PER_TASK {
+                controller.get_register(0).await.unwrap();
+            }
+        }));
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    start.elapsed()
+}
+
+#[tokio::main]
+async fn main() {
+    let swarm = FtSwarm::new(EmulatedSerialPort::new().with_latency(Duration::ZERO));
+    let controller = Controller::create(&swarm, "ftSwarm100", ()).await;
+    let controller = lock(&controller).await.clone();
+
+    let elapsed = sequential(&controller).await;
+    println!(
+        "sequential: {} requests in {:?}, {:?} per round trip",
+        SEQUENTIAL_REQUESTS, elapsed, elapsed / SEQUENTIAL_REQUESTS
+    );
+
+    let requests = PARALLEL_TASKS * REQUESTS_PER_TASK;
+    let elapsed = parallel(&controller).await;
+    println!(
+        "parallel: {} requests from {} tasks in {:?}, {:.0} requests/s",
+        requests, PARALLEL_TASKS, elapsed, requests as f64 / elapsed.as_secs_f64()
+    );
+}
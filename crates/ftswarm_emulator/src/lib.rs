@@ -1,12 +1,120 @@
-use std::collections::VecDeque;
-use log::{info, trace};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use log::{info, trace, warn};
 use ftswarm_proto::command::direct::FtSwarmDirectCommand;
 use ftswarm_proto::command::FtSwarmCommand;
 use ftswarm_proto::command::rpc::{FtSwarmRPCCommand, RpcFunction};
+use ftswarm_proto::command::enums::IOType;
 use ftswarm_proto::Deserialized;
 use ftswarm_serial::{SerialError, SwarmSerialPort};
+use crate::port::EmulatedPort;
+use crate::simulation::Simulation;
 
-pub struct EmulatedSerialPort(VecDeque<String>);
+mod port;
+pub mod server;
+pub mod simulation;
+
+/// How long the emulator takes to answer a command by default
+pub const DEFAULT_LATENCY: Duration = Duration::from_millis(10);
+
+/// The hostname of the emulated ftSwarm, its controller port has the same name
+pub const HOSTNAME: &str = "kelda";
+
+/// The serial number of the emulated ftSwarm, `ftSwarm100` also addresses the controller
+pub const SERIAL_NUMBER: u32 = 100;
+
+/// What the firmware prints once `startCLI` was received
+pub const CLI_BANNER: &str = "@@@ ftSwarmOS CLI started";
+
+/// The ports of the emulated ftSwarm, a combination of an ftSwarm and an ftSwarmControl
+const LAYOUT: &[(&str, IOType)] = &[
+    ("A1", IOType::Input),
+    ("A2", IOType::Input),
+    ("A3", IOType::Input),
+    ("A4", IOType::Input),
+    ("M1", IOType::Actor),
+    ("M2", IOType::Actor),
+    ("LED1", IOType::Led),
+    ("LED2", IOType::Led),
+    ("SERVO1", IOType::Servo),
+    ("JOY1", IOType::Joystick),
+    ("JOY2", IOType::Joystick),
+    ("S1", IOType::Button),
+    ("S2", IOType::Button),
+    ("S3", IOType::Button),
+    ("S4", IOType::Button),
+    ("F1", IOType::Button),
+    ("F2", IOType::Button),
+    ("OLED", IOType::Oled),
+    ("GYRO", IOType::Gyro),
+    ("I2C", IOType::I2c),
+];
+
+/// An emulated ftSwarm
+///
+/// Every port keeps what was set on it, so getters answer with the stored values. Commands for
+/// ports that don't exist are answered with the firmware's error. Clones share the same state,
+/// so a clone can be kept to set the emulated inputs after the port has been handed to an `FtSwarm`.
+#[derive(Clone)]
+pub struct EmulatedSerialPort {
+    state: Arc<Mutex<EmulatorState>>,
+    latency: Duration,
+}
+
+struct EmulatorState {
+    output: VecDeque<String>,
+    /// The ports by their upper case name
+    ports: HashMap<String, EmulatedPort>,
+    simulation: Option<Simulation>,
+}
+
+impl EmulatorState {
+    /// The upper-case key of the port `name` refers to, which may be qualified with the hostname or serial (`kelda.A1`)
+    fn port_name(&self, name: &str) -> Option<String> {
+        let name = name.to_uppercase();
+        let hostname = HOSTNAME.to_uppercase();
+        let serial = format!("FTSWARM{}", SERIAL_NUMBER);
+
+        let name = [&hostname, &serial].iter()
+            .find_map(|prefix| name.strip_prefix(prefix.as_str()).and_then(|rest| rest.strip_prefix('.')))
+            .unwrap_or(&name);
+        let name = if name == serial { &hostname } else { name };
+
+        self.ports.contains_key(name).then(|| name.to_string())
+    }
+
+    fn port(&mut self, name: &str) -> Option<&mut EmulatedPort> {
+        let name = self.port_name(name)?;
+        self.ports.get_mut(&name)
+    }
+
+    /// Set the value of an input and tell the subscribers if it changed by more than the hysteresis
+    fn set_input(&mut self, name: &str, value: i32) {
+        let Some(name) = self.port_name(name) else {
+            warn!("Emulator has no input {}", name);
+            return;
+        };
+
+        if self.ports.get_mut(&name).is_some_and(|port| port.set_input(value)) {
+            self.output.push_back(format!("S: {} {}", name, value));
+        }
+    }
+
+    /// Move the ports by `dt` on the virtual clock
+    fn step(&mut self, dt: Duration) {
+        for port in self.ports.values_mut() {
+            port.step(dt);
+        }
+    }
+
+    fn advance(&mut self, duration: Duration) {
+        if let Some(mut simulation) = self.simulation.take() {
+            simulation.advance(self, duration);
+            self.simulation = Some(simulation);
+        }
+    }
+}
 
 impl Default for EmulatedSerialPort {
     fn default() -> Self {
@@ -16,62 +124,133 @@ impl Default for EmulatedSerialPort {
 
 impl EmulatedSerialPort {
     pub fn new() -> EmulatedSerialPort {
-        EmulatedSerialPort(VecDeque::new())
+        let mut ports: HashMap<String, EmulatedPort> = LAYOUT.iter()
+            .map(|(name, io_type)| (name.to_string(), EmulatedPort::new(*io_type)))
+            .collect();
+        ports.insert(HOSTNAME.to_uppercase(), EmulatedPort::new(IOType::Input));
+
+        EmulatedSerialPort {
+            state: Arc::new(Mutex::new(EmulatorState { output: VecDeque::new(), ports, simulation: None })),
+            latency: DEFAULT_LATENCY,
+        }
+    }
+
+    /// Add a port that isn't part of the default layout
+    pub fn with_port(self, name: &str, io_type: IOType) -> Self {
+        self.state.lock().unwrap().ports.insert(name.to_uppercase(), EmulatedPort::new(io_type));
+        self
+    }
+
+    /// Set the value of the input `name`, e.g. `512` for an analog input or `1` for a pressed switch
+    ///
+    /// If the input is subscribed and the value changed by more than the hysteresis since the last
+    /// update, a subscription update is sent.
+    pub fn set_input(&self, name: &str, value: i32) {
+        self.state.lock().unwrap().set_input(name, value);
+    }
+
+    /// The value the input `name` is set to
+    pub fn input(&self, name: &str) -> Option<i32> {
+        self.state.lock().unwrap().port(name).map(|port| port.input())
+    }
+
+    /// The triggers set on the input `name`, as the commands that set them
+    pub fn triggers(&self, name: &str) -> Vec<String> {
+        self.state.lock().unwrap().port(name).map(|port| port.triggers()).unwrap_or_default()
+    }
+
+    /// Link outputs to inputs through the models of `simulation`, see [`simulation`]
+    pub fn with_simulation(self, simulation: Simulation) -> Self {
+        self.state.lock().unwrap().simulation = Some(simulation);
+        self
+    }
+
+    /// Advance the virtual clock of the simulation by `duration`
+    pub fn advance(&self, duration: Duration) {
+        self.state.lock().unwrap().advance(duration);
+    }
+
+    /// The time on the virtual clock of the simulation, zero without a simulation
+    pub fn now(&self) -> Duration {
+        self.state.lock().unwrap().simulation.as_ref().map_or(Duration::ZERO, Simulation::now)
+    }
+
+    /// Move the joystick `name` to the given left/right and front/back deflection
+    ///
+    /// If the joystick is subscribed and one axis moved further than the hysteresis, a
+    /// subscription update is sent.
+    pub fn move_joystick(&self, name: &str, lr: i32, fb: i32) {
+        let mut state = self.state.lock().unwrap();
+        let Some(name) = state.port_name(name) else {
+            warn!("Emulator has no joystick {}", name);
+            return;
+        };
+
+        if state.ports.get_mut(&name).is_some_and(|port| port.move_joystick(lr, fb)) {
+            state.output.push_back(format!("S: {} {} {}", name, lr, fb));
+        }
+    }
+
+    pub(crate) fn push(&self, line: &str) {
+        self.state.lock().unwrap().output.push_back(line.to_string());
+    }
+
+    /// Set how long the emulator takes to answer a command
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
     }
 
     fn handle_direct_command(&mut self, command: FtSwarmDirectCommand) {
         match command {
-            FtSwarmDirectCommand::Help => { self.0.push_back("Help".to_string()); }
-            FtSwarmDirectCommand::Setup => { self.0.push_back("Setup".to_string()); }
+            FtSwarmDirectCommand::Help => { self.push("Help"); }
+            FtSwarmDirectCommand::Setup => { self.push("Setup"); }
             FtSwarmDirectCommand::Halt => {}
-            FtSwarmDirectCommand::Whoami => {self.0.push_back("ftSwarm100/kelda".to_string()); }
-            FtSwarmDirectCommand::Uptime => { self.0.push_back("uptime: 31.000 s".to_string()); }
-            FtSwarmDirectCommand::StartCli => {}
+            FtSwarmDirectCommand::Whoami => { self.push(&format!("ftSwarm{}/{}", SERIAL_NUMBER, HOSTNAME)); }
+            FtSwarmDirectCommand::Uptime => { self.push("uptime: 31.000 s"); }
+            FtSwarmDirectCommand::StartCli => { self.push(CLI_BANNER); }
+            FtSwarmDirectCommand::Custom(_) => {}
         }
     }
 
     fn handle_rpc_command(&mut self, command: FtSwarmRPCCommand) {
-        let functions_to_ok = [RpcFunction::Show,
-            RpcFunction::TriggerUserEvent,
-            RpcFunction::SetMicroStepMode,
-            RpcFunction::SetSensorType,
-            RpcFunction::OnTrigger,
-            RpcFunction::SetActorType,
-            RpcFunction::SetSpeed,
-            RpcFunction::SetMotionType,
-            RpcFunction::SetPosition,
-            RpcFunction::SetOffset,
-            RpcFunction::SetColor,
-            RpcFunction::SetBrightness,
-            RpcFunction::SetRegister];
-
-        std::thread::sleep(std::time::Duration::from_millis(10));
-        match command.function {
-            RpcFunction::Subscribe => {}
-            _ => {
-                if functions_to_ok.contains(&command.function) {
-                    self.0.push_back("R: Ok".to_string());
-                    trace!("Emulator responded with Ok");
-                } else {
-                    self.0.push_back("R: 0".to_string());
-                    trace!("Emulator responded with 0");
-                }
-            }
+        std::thread::sleep(self.latency);
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(step) = state.simulation.as_ref().map(Simulation::per_command) {
+            state.advance(step);
         }
+
+        let response = match state.port(&command.target) {
+            Some(port) => match port.handle(&command) {
+                Some(response) => response,
+                None => return,
+            },
+            // Subscriptions are never answered, not even with an error
+            None if command.function == RpcFunction::Subscribe => {
+                warn!("Emulator can't subscribe to unknown port {}", command.target);
+                return;
+            }
+            // The firmware points at the offending part of the command, which is the port name
+            None => "^ Error: Port not found".to_string(),
+        };
+
+        trace!("Emulator responded with {}", response);
+        state.output.push_back(response);
     }
 }
 
 impl SwarmSerialPort for EmulatedSerialPort {
     fn available(&self) -> Result<bool, SerialError> {
-        Ok(!self.0.is_empty())
+        Ok(!self.state.lock().unwrap().output.is_empty())
     }
 
     fn read_line(&mut self) -> Result<String, SerialError> {
-        self.0.pop_front().ok_or(SerialError::Timeout)
+        self.state.lock().unwrap().output.pop_front().ok_or(SerialError::Timeout)
     }
 
     fn write_line(&mut self, line: String) -> Result<(), SerialError> {
-        let command = FtSwarmCommand::deserialize(&line).map_err(|_| SerialError::EncodingError)?;
+        let command = FtSwarmCommand::deserialize(&line).map_err(|err| SerialError::EncodingError(Box::new(err)))?;
         match command {
             FtSwarmCommand::RPC(command) => {
                 info!("Emulator received RPC command: {:?}", command);
@@ -85,7 +264,15 @@ impl SwarmSerialPort for EmulatedSerialPort {
         Ok(())
     }
 
-    fn block_until(&mut self, _: String) -> Result<(), SerialError> {
+    fn block_until(&mut self, line: String) -> Result<(), SerialError> {
+        // Like on a real serial port, everything up to the line is skipped
+        let mut state = self.state.lock().unwrap();
+        while let Some(skipped) = state.output.pop_front() {
+            if skipped.contains(&line) {
+                break;
+            }
+        }
+
         info!("Emulator has started");
         Ok(())
     }
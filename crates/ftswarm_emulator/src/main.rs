@@ -0,0 +1,128 @@
+//! Serves an emulated ftSwarm on a pseudo-terminal and a TCP port
+//!
+//! ```text
+//! ftswarm-emulator [--tcp ADDRESS] [--no-tcp] [--no-pty] [--link PATH] [--latency MS]
+//! ```
+
+use std::net::TcpListener;
+use std::process::exit;
+use std::thread;
+use std::time::Duration;
+use log::error;
+use ftswarm_emulator::{server, EmulatedSerialPort};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:7000";
+
+const USAGE: &str = "\
+Usage: ftswarm-emulator [OPTIONS]
+
+Options:
+    --tcp ADDRESS   Listen for TCP clients on ADDRESS (default: 127.0.0.1:7000)
+    --no-tcp        Don't listen for TCP clients
+    --no-pty        Don't open a pseudo-terminal
+    --link PATH     Create a symlink at PATH pointing to the pseudo-terminal
+    --latency MS    How long the emulator takes to answer a command (default: 10)
+    --help          Print this help";
+
+struct Options {
+    tcp: Option<String>,
+    pty: bool,
+    link: Option<String>,
+    latency: Option<Duration>,
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    exit(2);
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        tcp: Some(DEFAULT_ADDRESS.to_string()),
+        pty: cfg!(unix),
+        link: None,
+        latency: None,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| fail(&format!("{} needs a value", arg)));
+        match arg.as_str() {
+            "--tcp" => options.tcp = Some(value()),
+            "--no-tcp" => options.tcp = None,
+            "--no-pty" => options.pty = false,
+            "--link" => options.link = Some(value()),
+            "--latency" => {
+                let millis = value().parse().unwrap_or_else(|_| fail("--latency needs a number of milliseconds"));
+                options.latency = Some(Duration::from_millis(millis));
+            }
+            "--help" => {
+                println!("{}", USAGE);
+                exit(0);
+            }
+            _ => fail(&format!("Unknown option {}", arg)),
+        }
+    }
+
+    if options.tcp.is_none() && !options.pty {
+        fail("Nothing to serve on");
+    }
+
+    options
+}
+
+#[cfg(unix)]
+fn open_pty(emulator: EmulatedSerialPort, link: Option<String>) -> std::io::Result<thread::JoinHandle<std::io::Result<()>>> {
+    let pty = server::Pty::open()?;
+    println!("Serving on {}", pty.path().display());
+
+    if let Some(link) = link {
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(pty.path(), &link)?;
+        println!("Linked {} to {}", link, pty.path().display());
+    }
+
+    Ok(thread::spawn(move || pty.serve(emulator)))
+}
+
+#[cfg(not(unix))]
+fn open_pty(_: EmulatedSerialPort, _: Option<String>) -> std::io::Result<thread::JoinHandle<std::io::Result<()>>> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "Pseudo-terminals need a Unix system"))
+}
+
+fn main() {
+    env_logger::init();
+    let options = parse_options();
+
+    let mut emulator = EmulatedSerialPort::new();
+    if let Some(latency) = options.latency {
+        emulator = emulator.with_latency(latency);
+    }
+
+    let mut servers = Vec::new();
+    if options.pty {
+        match open_pty(emulator.clone(), options.link) {
+            Ok(server) => servers.push(server),
+            Err(err) => {
+                eprintln!("Failed to open a pseudo-terminal: {}", err);
+                exit(1);
+            }
+        }
+    }
+
+    if let Some(address) = options.tcp {
+        let listener = TcpListener::bind(&address).unwrap_or_else(|err| {
+            eprintln!("Failed to listen on {}: {}", address, err);
+            exit(1);
+        });
+        println!("Serving on tcp://{}", listener.local_addr().map_or(address, |address| address.to_string()));
+        servers.push(thread::spawn(move || server::serve_tcp(emulator, listener)));
+    }
+
+    for server in servers {
+        if let Err(err) = server.join().expect("Server thread panicked") {
+            error!("Server failed: {}", err);
+            exit(1);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use ftswarm_proto::command::argument::Argument;
+use ftswarm_proto::command::enums::{ActorType, IOType, MicroStepMode, MotionType, SensorType};
+use ftswarm_proto::command::rpc::{FtSwarmRPCCommand, RpcFunction};
+use ftswarm_proto::{IdOf, NameOf, Serialized};
+
+/// The state of a single port of the emulated ftSwarm
+pub(crate) struct EmulatedPort {
+    pub(crate) io_type: IOType,
+    sensor_type: SensorType,
+    actor_type: ActorType,
+    /// The hysteresis, if the port is subscribed
+    subscription: Option<i32>,
+    /// The value of an input, and the value that was last sent to subscribers
+    input: (i32, i32),
+    joystick: EmulatedJoystick,
+    stepper: Option<EmulatedStepper>,
+    speed: i64,
+    motion_type: MotionType,
+    position: i64,
+    offset: i64,
+    color: i64,
+    brightness: i64,
+    registers: HashMap<i64, i64>,
+    micro_step_mode: MicroStepMode,
+    /// The triggers set on an input, one per function and event
+    triggers: Vec<FtSwarmRPCCommand>,
+}
+
+#[derive(Default, Clone, Copy)]
+struct EmulatedJoystick {
+    position: (i32, i32),
+    /// The position that was last sent to subscribers
+    reported: (i32, i32),
+}
+
+/// A stepper motor, moving at the speed of its port on the virtual clock of a simulation
+#[derive(Default)]
+struct EmulatedStepper {
+    position: i64,
+    /// Steps moved in total, not affected by setting the position
+    travel: i64,
+    /// Fractions of a step that were moved
+    progress: f64,
+}
+
+fn int_arg(command: &FtSwarmRPCCommand, index: usize) -> i64 {
+    match command.args.get(index) {
+        Some(Argument::Int(value)) => *value,
+        _ => 0,
+    }
+}
+
+impl EmulatedStepper {
+    /// Handle a command sent to this stepper, returns the response
+    fn handle(&mut self, command: &FtSwarmRPCCommand) -> Option<String> {
+        let response = match command.function {
+            RpcFunction::SetPosition => { self.position = int_arg(command, 0); "Ok".to_string() }
+            RpcFunction::GetPosition => self.position.to_string(),
+            _ => return None,
+        };
+
+        Some(response)
+    }
+
+    fn step(&mut self, speed: i64, dt: Duration) {
+        self.progress += speed.abs() as f64 * dt.as_secs_f64();
+        let steps = self.progress.floor() as i64;
+        self.progress -= steps as f64;
+
+        let moved = steps * speed.signum();
+        self.position += moved;
+        self.travel += moved;
+    }
+}
+
+impl EmulatedPort {
+    pub(crate) fn new(io_type: IOType) -> Self {
+        EmulatedPort {
+            io_type,
+            sensor_type: SensorType::Digital,
+            actor_type: ActorType::Motor,
+            subscription: None,
+            input: (0, 0),
+            joystick: EmulatedJoystick::default(),
+            stepper: None,
+            speed: 0,
+            motion_type: MotionType::Coast,
+            position: 0,
+            offset: 0,
+            color: 0,
+            brightness: 0,
+            registers: HashMap::new(),
+            micro_step_mode: MicroStepMode::FullStep,
+            triggers: Vec::new(),
+        }
+    }
+
+    /// Advance by `dt` on the virtual clock
+    pub(crate) fn step(&mut self, dt: Duration) {
+        let speed = self.speed();
+        if let Some(stepper) = &mut self.stepper {
+            stepper.step(speed, dt);
+        }
+    }
+
+    /// The speed of an actor, 0 unless it is switched on
+    pub(crate) fn speed(&self) -> i64 {
+        if self.motion_type == MotionType::On { self.speed } else { 0 }
+    }
+
+    pub(crate) fn servo_position(&self) -> i64 {
+        self.position + self.offset
+    }
+
+    pub(crate) fn stepper_travel(&self) -> i64 {
+        self.stepper.as_ref().map_or(0, |stepper| stepper.travel)
+    }
+
+    pub(crate) fn input(&self) -> i32 {
+        self.input.0
+    }
+
+    pub(crate) fn triggers(&self) -> Vec<String> {
+        self.triggers.iter().map(Serialized::serialize).collect()
+    }
+
+    /// Whether a change from `reported` to `value` is reported to subscribers
+    fn exceeds_hysteresis(&self, reported: i32, value: i32) -> bool {
+        self.subscription.is_some_and(|hysteresis| (value - reported).abs() > hysteresis)
+    }
+
+    /// Set the value of an input, returns whether subscribers are to be told
+    pub(crate) fn set_input(&mut self, value: i32) -> bool {
+        self.input.0 = value;
+        if !self.exceeds_hysteresis(self.input.1, value) {
+            return false;
+        }
+
+        self.input.1 = value;
+        true
+    }
+
+    /// Move a joystick, returns whether subscribers are to be told
+    pub(crate) fn move_joystick(&mut self, lr: i32, fb: i32) -> bool {
+        self.joystick.position = (lr, fb);
+        let (reported_lr, reported_fb) = self.joystick.reported;
+        if !self.exceeds_hysteresis(reported_lr, lr) && !self.exceeds_hysteresis(reported_fb, fb) {
+            return false;
+        }
+
+        self.joystick.reported = (lr, fb);
+        true
+    }
+
+    /// Handle a command sent to this port, returns the response line.
+    /// Subscriptions aren't answered
+    pub(crate) fn handle(&mut self, command: &FtSwarmRPCCommand) -> Option<String> {
+        if let Some(response) = self.stepper.as_mut().and_then(|stepper| stepper.handle(command)) {
+            return Some(format!("R: {}", response));
+        }
+
+        let arg = |index| int_arg(command, index);
+        let ok = || Some("Ok".to_string());
+
+        let response = match command.function {
+            RpcFunction::Subscribe => {
+                self.subscription = Some(arg(0) as i32);
+                self.input.1 = self.input.0;
+                self.joystick.reported = self.joystick.position;
+                return None;
+            }
+            RpcFunction::SetSensorType => {
+                if let Some(Argument::SensorType(sensor_type)) = command.args.first() {
+                    self.sensor_type = *sensor_type;
+                }
+                ok()
+            }
+            RpcFunction::GetSensorType => Some(self.sensor_type.id().to_string()),
+            RpcFunction::SetActorType => {
+                if let Some(Argument::ActorType(actor_type)) = command.args.first() {
+                    self.actor_type = *actor_type;
+                    self.stepper = (*actor_type == ActorType::Stepper).then(EmulatedStepper::default);
+                }
+                ok()
+            }
+            RpcFunction::GetActorType => Some(self.actor_type.id().to_string()),
+            RpcFunction::GetIOType => Some(self.io_type.id().to_string()),
+            RpcFunction::GetValue if self.io_type == IOType::Joystick => {
+                let (lr, fb) = self.joystick.position;
+                Some(format!("{} {}", lr, fb))
+            }
+            RpcFunction::GetValue => Some(self.input.0.to_string()),
+            RpcFunction::SetSpeed => { self.speed = arg(0); ok() }
+            RpcFunction::GetSpeed => Some(self.speed.to_string()),
+            RpcFunction::SetMotionType => {
+                if let Some(Argument::MotionType(motion_type)) = command.args.first() {
+                    self.motion_type = *motion_type;
+                }
+                ok()
+            }
+            RpcFunction::GetMotionType => Some(self.motion_type.id().to_string()),
+            RpcFunction::SetPosition => { self.position = arg(0); ok() }
+            RpcFunction::GetPosition => Some(self.position.to_string()),
+            RpcFunction::SetOffset => { self.offset = arg(0); ok() }
+            RpcFunction::GetOffset => Some(self.offset.to_string()),
+            RpcFunction::SetColor => { self.color = arg(0); ok() }
+            RpcFunction::GetColor => Some(self.color.to_string()),
+            RpcFunction::SetBrightness => { self.brightness = arg(0); ok() }
+            RpcFunction::GetBrightness => Some(self.brightness.to_string()),
+            RpcFunction::SetMicroStepMode => {
+                if let Some(Argument::MicroStepMode(mode)) = command.args.first() {
+                    self.micro_step_mode = *mode;
+                }
+                ok()
+            }
+            RpcFunction::GetMicroStepMode => Some(self.micro_step_mode.id().to_string()),
+            RpcFunction::SetRegister => { self.registers.insert(arg(0), arg(1)); ok() }
+            RpcFunction::GetRegister => Some(self.registers.get(&arg(0)).copied().unwrap_or(0).to_string()),
+            // Setting a trigger for an event again replaces it
+            RpcFunction::OnTrigger | RpcFunction::OnTriggerLR | RpcFunction::OnTriggerFB => {
+                let event = command.args.first().map(Argument::serialize);
+                self.triggers.retain(|trigger| trigger.function != command.function || trigger.args.first().map(Argument::serialize) != event);
+                self.triggers.push(command.clone());
+                ok()
+            }
+            // Readings of inputs that aren't modelled
+            RpcFunction::GetVoltage
+            | RpcFunction::GetResistance
+            | RpcFunction::GetKelvin
+            | RpcFunction::GetCelsius
+            | RpcFunction::GetFahrenheit
+            | RpcFunction::GetToggle => Some("0".to_string()),
+            // Answered with an error like the firmware's, rather than pretending they worked
+            RpcFunction::Show | RpcFunction::TriggerUserEvent | RpcFunction::Custom(_) => {
+                return Some(format!("^ Error: {} isn't emulated", command.function.name()));
+            }
+        };
+
+        response.map(|response| format!("R: {}", response))
+    }
+}
@@ -0,0 +1,159 @@
+//! Serve the emulator outside of the process, on a TCP port or a pseudo-terminal
+//!
+//! Clients talk to the emulator like to a real ftSwarm: they send `startCLI`, wait for the
+//! [`CLI_BANNER`](crate::CLI_BANNER) and then send one command per line. A client is served until it
+//! disconnects, the next one finds the ports as the last one left them.
+//!
+//! ```no_run
+//! use std::net::TcpListener;
+//! use ftswarm_emulator::EmulatedSerialPort;
+//! use ftswarm_emulator::server;
+//!
+//! let listener = TcpListener::bind("127.0.0.1:7000").unwrap();
+//! server::serve_tcp(EmulatedSerialPort::new(), listener).unwrap();
+//! ```
+
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use log::{info, warn};
+use ftswarm_serial::{SerialError, SwarmSerialPort};
+use crate::EmulatedSerialPort;
+
+/// How often the emulator is checked for lines to send
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Send the lines of the emulator to `writer` until `stop` is set or writing fails
+fn forward(mut emulator: EmulatedSerialPort, mut writer: impl Write, stop: &AtomicBool) {
+    while !stop.load(Ordering::Relaxed) {
+        if !emulator.available().unwrap_or(false) {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+
+        let Ok(line) = emulator.read_line() else { continue };
+        if let Err(err) = writer.write_all(format!("{}\r\n", line).as_bytes()).and_then(|_| writer.flush()) {
+            warn!("Emulator failed to send {:?}: {}", line, err);
+            return;
+        }
+    }
+}
+
+/// Serve a single client, reading its commands from `reader` and sending the responses to `writer`
+///
+/// Returns once the client disconnects. Lines the firmware couldn't parse are answered with an error.
+pub fn serve(mut emulator: EmulatedSerialPort, reader: impl Read, writer: impl Write + Send + 'static) -> std::io::Result<()> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let forwarder = {
+        let emulator = emulator.clone();
+        let stop = stop.clone();
+        thread::spawn(move || forward(emulator, writer, &stop))
+    };
+
+    let mut result = Ok(());
+    for line in BufReader::new(reader).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                result = Err(err);
+                break;
+            }
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match emulator.write_line(line.to_string()) {
+            Ok(()) => {}
+            Err(SerialError::EncodingError(err)) => emulator.push(&format!("^ Error: {}", err)),
+            Err(err) => warn!("Emulator failed to handle {:?}: {}", line, err),
+        }
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    forwarder.join().expect("Forwarding thread panicked");
+    result
+}
+
+/// Accept clients on `listener` and serve them one after another, like a serial port that can
+/// only be opened once
+pub fn serve_tcp(emulator: EmulatedSerialPort, listener: TcpListener) -> std::io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let peer = stream.peer_addr()?;
+        info!("Emulator serving {}", peer);
+
+        if let Err(err) = serve(emulator.clone(), stream.try_clone()?, stream) {
+            warn!("Connection to {} failed: {}", peer, err);
+        }
+        info!("{} disconnected", peer);
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+pub use pty::Pty;
+
+#[cfg(unix)]
+mod pty {
+    use std::fs::File;
+    use std::os::fd::OwnedFd;
+    use std::path::{Path, PathBuf};
+    use std::thread;
+    use std::time::Duration;
+    use log::info;
+    use nix::pty::openpty;
+    use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+    use nix::unistd::ttyname;
+    use super::{serve, ErrorKind};
+    use crate::EmulatedSerialPort;
+
+    /// How often a pseudo-terminal without a client is checked for a new one
+    const RECONNECT_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// A pseudo-terminal that looks like the serial port of an ftSwarm to other programs
+    pub struct Pty {
+        master: OwnedFd,
+        path: PathBuf,
+    }
+
+    impl Pty {
+        pub fn open() -> std::io::Result<Self> {
+            let pty = openpty(None, None)?;
+            let path = ttyname(&pty.slave)?;
+
+            // No echo or line editing, the bytes are passed through like on a serial port
+            let mut termios = tcgetattr(&pty.slave)?;
+            cfmakeraw(&mut termios);
+            tcsetattr(&pty.slave, SetArg::TCSANOW, &termios)?;
+
+            Ok(Pty { master: pty.master, path })
+        }
+
+        /// The device clients open, e.g. `/dev/pts/3`
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// Serve the clients that open the device, forever
+        pub fn serve(self, emulator: EmulatedSerialPort) -> std::io::Result<()> {
+            info!("Emulator serving on {}", self.path.display());
+            let master = File::from(self.master);
+
+            loop {
+                match serve(emulator.clone(), master.try_clone()?, master.try_clone()?) {
+                    // Reading fails while no client has the device open
+                    Err(err) if err.raw_os_error() == Some(nix::libc::EIO) => thread::sleep(RECONNECT_INTERVAL),
+                    Err(err) if err.kind() != ErrorKind::Interrupted => return Err(err),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! Simple models that link the outputs of the emulated ftSwarm to its inputs
+//!
+//! A [`Simulation`] runs on a virtual clock, which only moves when the emulator is told to
+//! [advance](crate::EmulatedSerialPort::advance) it, or by a fixed step for every command the
+//! emulator receives. Steppers move at their speed on the same clock while they are switched on.
+//!
+//! ```
+//! use std::time::Duration;
+//! use ftswarm_emulator::EmulatedSerialPort;
+//! use ftswarm_emulator::simulation::{Encoder, Simulation, Slide};
+//!
+//! let emulator = EmulatedSerialPort::new().with_simulation(
+//!     Simulation::new()
+//!         .with_model(Encoder::new("M1", "A2", 120.0))
+//!         .with_model(Slide::new("M2", "A1", 3.0, 1.5))
+//! );
+//! emulator.advance(Duration::from_secs(1));
+//! ```
+
+use std::time::Duration;
+use crate::EmulatorState;
+
+/// How far the clock moves in a single step of the models by default
+pub const DEFAULT_TICK: Duration = Duration::from_millis(10);
+
+/// A model that is advanced with the virtual clock
+pub trait Model: Send {
+    /// Advance the model by `dt`, reading the outputs from and setting the inputs of `world`
+    fn step(&mut self, world: &mut World, dt: Duration);
+}
+
+/// The models of an emulated ftSwarm and its virtual clock
+pub struct Simulation {
+    models: Vec<Box<dyn Model>>,
+    now: Duration,
+    tick: Duration,
+    per_command: Duration,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        Simulation {
+            models: Vec::new(),
+            now: Duration::ZERO,
+            tick: DEFAULT_TICK,
+            per_command: Duration::ZERO,
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Model + 'static) -> Self {
+        self.models.push(Box::new(model));
+        self
+    }
+
+    /// Set how far the clock moves in a single step of the models
+    pub fn with_tick(mut self, tick: Duration) -> Self {
+        self.tick = tick.max(Duration::from_micros(1));
+        self
+    }
+
+    /// Advance the clock by `step` for every command the emulator receives
+    ///
+    /// This lets code that polls the ftSwarm make progress without a task that advances the clock.
+    pub fn advancing_per_command(mut self, step: Duration) -> Self {
+        self.per_command = step;
+        self
+    }
+
+    /// The time on the virtual clock
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    pub(crate) fn per_command(&self) -> Duration {
+        self.per_command
+    }
+
+    pub(crate) fn advance(&mut self, state: &mut EmulatorState, duration: Duration) {
+        let end = self.now + duration;
+        while self.now < end {
+            let dt = self.tick.min(end - self.now);
+            state.step(dt);
+
+            let mut world = World { state };
+            for model in &mut self.models {
+                model.step(&mut world, dt);
+            }
+            self.now += dt;
+        }
+    }
+}
+
+/// The ports of the emulated ftSwarm, as seen by a [`Model`]
+pub struct World<'a> {
+    state: &'a mut EmulatorState,
+}
+
+impl World<'_> {
+    /// The speed of an actor from -255 to 255, 0 unless it is switched on
+    pub fn speed(&mut self, actor: &str) -> i32 {
+        self.state.port(actor).map(|port| port.speed()).unwrap_or(0) as i32
+    }
+
+    /// The position of a servo including its offset
+    pub fn servo_position(&mut self, servo: &str) -> i32 {
+        self.state.port(servo).map(|port| port.servo_position()).unwrap_or(0) as i32
+    }
+
+    /// How many steps a stepper has moved, not affected by setting its position
+    pub fn stepper_travel(&mut self, stepper: &str) -> i64 {
+        self.state.port(stepper).map(|port| port.stepper_travel()).unwrap_or(0)
+    }
+
+    pub fn input(&mut self, input: &str) -> i32 {
+        self.state.port(input).map(|port| port.input()).unwrap_or(0)
+    }
+
+    /// Set an input, subscribers are told like with [`EmulatedSerialPort::set_input`](crate::EmulatedSerialPort::set_input)
+    pub fn set_input(&mut self, input: &str, value: i32) {
+        self.state.set_input(input, value);
+    }
+}
+
+/// A motor that advances a counter or encoder input proportionally to its speed
+pub struct Encoder {
+    motor: String,
+    input: String,
+    counts_per_second: f64,
+    count: f64,
+}
+
+impl Encoder {
+    /// `counts_per_second` is the rate at full speed
+    pub fn new(motor: &str, input: &str, counts_per_second: f64) -> Self {
+        Encoder {
+            motor: motor.to_string(),
+            input: input.to_string(),
+            counts_per_second,
+            count: 0.0,
+        }
+    }
+}
+
+impl Model for Encoder {
+    fn step(&mut self, world: &mut World, dt: Duration) {
+        let speed = world.speed(&self.motor) as f64 / 255.0;
+        self.count += speed * self.counts_per_second * dt.as_secs_f64();
+        world.set_input(&self.input, self.count.round() as i32);
+    }
+}
+
+/// A lamp that lights up an LDR, the reading moves linearly between `dark` and `bright` with the
+/// lamp's brightness
+pub struct Light {
+    lamp: String,
+    ldr: String,
+    dark: i32,
+    bright: i32,
+}
+
+impl Light {
+    pub fn new(lamp: &str, ldr: &str, dark: i32, bright: i32) -> Self {
+        Light {
+            lamp: lamp.to_string(),
+            ldr: ldr.to_string(),
+            dark,
+            bright,
+        }
+    }
+}
+
+impl Model for Light {
+    fn step(&mut self, world: &mut World, _: Duration) {
+        let brightness = world.speed(&self.lamp).abs() as f64 / 255.0;
+        let reading = self.dark as f64 + (self.bright - self.dark) as f64 * brightness;
+        world.set_input(&self.ldr, reading.round() as i32);
+    }
+}
+
+/// A potentiometer turned by a servo, read as `offset + scale * position` on an analog input
+pub struct Potentiometer {
+    servo: String,
+    input: String,
+    scale: f64,
+    offset: i32,
+}
+
+impl Potentiometer {
+    pub fn new(servo: &str, input: &str, scale: f64, offset: i32) -> Self {
+        Potentiometer {
+            servo: servo.to_string(),
+            input: input.to_string(),
+            scale,
+            offset,
+        }
+    }
+}
+
+impl Model for Potentiometer {
+    fn step(&mut self, world: &mut World, _: Duration) {
+        let position = world.servo_position(&self.servo) as f64;
+        world.set_input(&self.input, self.offset + (self.scale * position).round() as i32);
+    }
+}
+
+enum Drive {
+    /// A motor, moving the slide by this many units per second at full speed
+    Motor(f64),
+    /// A stepper, moving the slide by one unit per step. Holds the travel seen in the last step
+    Stepper(i64),
+}
+
+/// A slide driven by a motor or stepper, which presses a limit switch at the end of its travel
+///
+/// The slide starts at 0 and the switch is at the given distance, which is negative if the switch
+/// is behind the slide. The slide can't move past the switch.
+pub struct Slide {
+    drive_port: String,
+    switch: String,
+    drive: Drive,
+    switch_at: f64,
+    position: f64,
+}
+
+impl Slide {
+    /// A slide driven by `motor`, with the switch `revolutions` away. At full speed, the motor
+    /// turns `revolutions_per_second` times
+    pub fn new(motor: &str, switch: &str, revolutions: f64, revolutions_per_second: f64) -> Self {
+        Slide {
+            drive_port: motor.to_string(),
+            switch: switch.to_string(),
+            drive: Drive::Motor(revolutions_per_second),
+            switch_at: revolutions,
+            position: 0.0,
+        }
+    }
+
+    /// A slide driven by `stepper`, with the switch `steps` away
+    pub fn stepper(stepper: &str, switch: &str, steps: i64) -> Self {
+        Slide {
+            drive_port: stepper.to_string(),
+            switch: switch.to_string(),
+            drive: Drive::Stepper(0),
+            switch_at: steps as f64,
+            position: 0.0,
+        }
+    }
+
+    fn is_pressed(&self) -> bool {
+        if self.switch_at >= 0.0 {
+            self.position >= self.switch_at
+        } else {
+            self.position <= self.switch_at
+        }
+    }
+}
+
+impl Model for Slide {
+    fn step(&mut self, world: &mut World, dt: Duration) {
+        let moved = match &mut self.drive {
+            Drive::Motor(revolutions_per_second) => {
+                world.speed(&self.drive_port) as f64 / 255.0 * *revolutions_per_second * dt.as_secs_f64()
+            }
+            Drive::Stepper(last_travel) => {
+                let travel = world.stepper_travel(&self.drive_port);
+                let moved = travel - *last_travel;
+                *last_travel = travel;
+                moved as f64
+            }
+        };
+
+        self.position += moved;
+        if self.is_pressed() {
+            self.position = self.switch_at;
+        }
+
+        world.set_input(&self.switch, self.is_pressed() as i32);
+    }
+}
@@ -1,10 +1,449 @@
 use ftswarm::prelude::*;
-use crate::EmulatedSerialPort;
+use ftswarm::proto::command::enums::{ActorType, IOType, MicroStepMode, MotionType, SensorType};
+use ftswarm::proto::command::FtSwarmCommand;
+use ftswarm::proto::command::direct::FtSwarmDirectCommand;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use ftswarm_serial::{AsyncLinePort, BlockingAdapter, SwarmSerialPort};
+use ftswarm_serial::fault::{Fault, FaultInjector, FaultSchedule};
+use crate::{EmulatedSerialPort, HOSTNAME};
+use crate::server;
+use crate::simulation::{Encoder, Light, Potentiometer, Simulation, Slide};
 
 #[tokio::test]
 pub async fn test_controller() {
     let ftswarm = FtSwarm::new(EmulatedSerialPort::new());
-    let controller = Controller::create(&ftswarm, "controller", ()).await;
+    let controller = Controller::create(&ftswarm, HOSTNAME, ()).await;
 
-    controller.lock().unwrap().set_register(0, 1).await.unwrap();
+    let controller = lock(&controller).await.clone();
+    controller.set_register(0, 1).await.unwrap();
+}
+
+#[tokio::test]
+pub async fn test_joystick() {
+    let emulator = EmulatedSerialPort::new().with_latency(Duration::ZERO);
+    emulator.move_joystick("JOY1", -20, 35);
+
+    let ftswarm = FtSwarm::new(emulator.clone());
+    let joystick = Joystick::create(&ftswarm, "JOY1", Hysteresis(5)).await;
+
+    let mut watch = {
+        let joystick = lock(&joystick).await;
+        assert_eq!(joystick.value, JoystickPosition::new(-20, 35));
+        joystick.watch()
+    };
+
+    // Moves within the hysteresis aren't reported
+    emulator.move_joystick("JOY1", -18, 37);
+    emulator.move_joystick("JOY1", 50, 37);
+    watch.changed().await.unwrap();
+    assert_eq!(watch.borrow_and_update().value, JoystickPosition::new(50, 37));
+
+    let joystick = lock(&joystick).await.clone();
+    assert_eq!(joystick.read_value().await.unwrap(), JoystickPosition::new(50, 37));
+}
+
+#[tokio::test]
+pub async fn test_stepper() {
+    let ftswarm = FtSwarm::new(EmulatedSerialPort::new().with_latency(Duration::ZERO));
+    let controller = Controller::create(&ftswarm, HOSTNAME, ()).await;
+    let stepper = Stepper::create(&ftswarm, "M1", ()).await;
+
+    let controller = lock(&controller).await.clone();
+    controller.set_micro_step_mode(MicroStepMode::QuarterStep).await.unwrap();
+    assert_eq!(controller.get_micro_step_mode().await.unwrap(), MicroStepMode::QuarterStep);
+
+    let stepper = lock(&stepper).await.clone();
+    stepper.set_speed(400, Direction::Backward).await.unwrap();
+    assert_eq!(stepper.get_speed().await.unwrap(), -400);
+
+    stepper.set_position(150).await.unwrap();
+    assert_eq!(stepper.get_position().await.unwrap(), 150);
+
+    stepper.brake().await.unwrap();
+    assert_eq!(stepper.get_motion_type().await.unwrap(), MotionType::Brake);
+
+    stepper.set_position(0).await.unwrap();
+    assert_eq!(stepper.get_position().await.unwrap(), 0);
+}
+
+#[tokio::test]
+pub async fn test_on_trigger() {
+    let emulator = EmulatedSerialPort::new().with_latency(Duration::ZERO);
+    let ftswarm = FtSwarm::new(emulator.clone());
+    let switch = Switch::create(&ftswarm, "A1", NormallyOpen::Open).await;
+    let joystick = Joystick::create(&ftswarm, "JOY1", Hysteresis(0)).await;
+    let lamp = Lamp::create(&ftswarm, "M2", ()).await;
+
+    let lamp = lock(&lamp).await.clone();
+    let switch = lock(&switch).await.clone();
+    switch.on_trigger(TriggerEvent::Up, &lamp, 255).await.unwrap();
+
+    let joystick = lock(&joystick).await.clone();
+    joystick.on_trigger_lr_forward(TriggerEvent::Value, &lamp).await.unwrap();
+    joystick.on_trigger_fb(TriggerEvent::Down, &lamp, 0).await.unwrap();
+    joystick.on_trigger_fb(TriggerEvent::Down, &lamp, 128).await.unwrap();
+
+    assert_eq!(emulator.triggers("A1"), ["A1.onTrigger(0, M2, 255)"]);
+    // The later trigger for the same event replaces the earlier one
+    assert_eq!(emulator.triggers("JOY1"), ["JOY1.onTriggerLR(2, M2)", "JOY1.onTriggerFB(1, M2, 128)"]);
+    assert!(emulator.triggers("A2").is_empty());
+}
+
+#[tokio::test]
+pub async fn test_port_types() {
+    let ftswarm = FtSwarm::new(EmulatedSerialPort::new().with_latency(Duration::ZERO));
+    let switch = Switch::create(&ftswarm, "A1", NormallyOpen::Open).await;
+    let motor = Motor::create(&ftswarm, "M1", ()).await;
+
+    let switch = lock(&switch).await.clone();
+    assert_eq!(switch.get_sensor_type().await.unwrap(), SensorType::Switch);
+    assert_eq!(switch.get_io_type().await.unwrap(), IOType::Input);
+
+    let motor = lock(&motor).await.clone();
+    assert_eq!(motor.get_actor_type().await.unwrap(), ActorType::Motor);
+    assert_eq!(motor.get_io_type().await.unwrap(), IOType::Actor);
+}
+
+#[tokio::test]
+pub async fn test_motion_type() {
+    let ftswarm = FtSwarm::new(EmulatedSerialPort::new().with_latency(Duration::ZERO));
+    let motor = Motor::create(&ftswarm, "M1", ()).await;
+
+    let motor = lock(&motor).await.clone();
+    assert_eq!(motor.get_motion_type().await.unwrap(), MotionType::Coast);
+
+    // Setting the speed doesn't switch the motor on
+    motor.set(-300).await.unwrap();
+    assert_eq!(motor.get_motion_type().await.unwrap(), MotionType::Coast);
+    motor.set_motion_type(MotionType::On).await.unwrap();
+    assert_eq!(motor.get_speed().await.unwrap(), -255);
+    assert_eq!(motor.get_motion_type().await.unwrap(), MotionType::On);
+
+    motor.brake().await.unwrap();
+    assert_eq!(motor.get_motion_type().await.unwrap(), MotionType::Brake);
+}
+
+#[tokio::test]
+pub async fn test_stored_values() {
+    let ftswarm = FtSwarm::new(EmulatedSerialPort::new().with_latency(Duration::ZERO));
+    let servo = Servo::create(&ftswarm, "SERVO1", ()).await;
+    let led = Led::create(&ftswarm, "kelda.LED1", ()).await;
+    let controller = Controller::create(&ftswarm, "ftSwarm100", ()).await;
+
+    let servo = lock(&servo).await.clone();
+    servo.set_position(45).await.unwrap();
+    servo.set_offset(-3).await.unwrap();
+    assert_eq!(servo.get_position().await.unwrap(), 45);
+    assert_eq!(servo.get_offset().await.unwrap(), -3);
+
+    let led = lock(&led).await.clone();
+    led.set_color(LedColor::rgb(12, 34, 56)).await.unwrap();
+    led.set_brightness(128).await.unwrap();
+    assert_eq!(led.get_color().await.unwrap(), LedColor::rgb(12, 34, 56));
+    assert_eq!(led.get_brightness().await.unwrap(), 128);
+
+    let controller = lock(&controller).await.clone();
+    controller.set_register(3, 42).await.unwrap();
+    assert_eq!(controller.get_register(3).await.unwrap(), 42);
+    assert_eq!(controller.get_register(4).await.unwrap(), 0);
+}
+
+#[tokio::test]
+pub async fn test_unknown_port() {
+    let ftswarm = FtSwarm::new(EmulatedSerialPort::new().with_latency(Duration::ZERO));
+
+    match Switch::try_create(&ftswarm, "A9", NormallyOpen::Open).await {
+        Err(FtSwarmError::Setup { source, .. }) => match *source {
+            FtSwarmError::Firmware(error) => assert_eq!(error.message, "Port not found"),
+            other => panic!("Expected firmware error, got {:?}", other),
+        },
+        Err(other) => panic!("Expected setup error, got {:?}", other),
+        Ok(_) => panic!("Expected A9 to be unknown"),
+    }
+
+    // The connection is still usable afterwards
+    let ftswarm = FtSwarm::new(EmulatedSerialPort::new().with_latency(Duration::ZERO).with_port("A9", IOType::Input));
+    Switch::try_create(&ftswarm, "A9", NormallyOpen::Open).await.unwrap();
+}
+
+#[tokio::test]
+pub async fn test_unmodelled_function() {
+    let ftswarm = FtSwarm::new(EmulatedSerialPort::new().with_latency(Duration::ZERO));
+    let controller = Controller::create(&ftswarm, HOSTNAME, ()).await;
+    let controller = lock(&controller).await.clone();
+
+    match controller.show().await {
+        Err(FtSwarmError::Firmware(error)) => assert_eq!(error.message, "show isn't emulated"),
+        other => panic!("Expected firmware error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+pub async fn test_set_input() {
+    let emulator = EmulatedSerialPort::new().with_latency(Duration::ZERO);
+    emulator.set_input("A2", 100);
+
+    let ftswarm = FtSwarm::new(emulator.clone());
+    let analog = Analog::create(&ftswarm, "A2", Hysteresis(10)).await;
+    let switch = Switch::create(&ftswarm, "A1", NormallyOpen::Open).await;
+
+    let mut values = {
+        let analog = lock(&analog).await;
+        assert_eq!(analog.value, 100);
+        analog.watch()
+    };
+
+    // Changes within the hysteresis aren't reported
+    emulator.set_input("A2", 105);
+    assert!(tokio::time::timeout(Duration::from_millis(50), values.changed()).await.is_err());
+
+    emulator.set_input("A2", 512);
+    values.changed().await.unwrap();
+    assert_eq!(values.borrow_and_update().value, 512);
+    assert_eq!(lock(&analog).await.value, 512);
+
+    let mut pressed = lock(&switch).await.watch();
+    emulator.set_input("A1", 1);
+    pressed.changed().await.unwrap();
+    assert!(pressed.borrow_and_update().value);
+}
+
+#[test]
+pub fn test_update_port_names() {
+    let mut emulator = EmulatedSerialPort::new().with_latency(Duration::ZERO);
+    emulator.write_line("A2.subscribe(0)".to_string()).unwrap();
+    emulator.write_line("JOY1.subscribe(0)".to_string()).unwrap();
+
+    // Updates name the port like the ftSwarm does, whatever name it was set by
+    emulator.set_input("a2", 5);
+    emulator.move_joystick(&format!("{}.joy1", HOSTNAME.to_lowercase()), 3, 4);
+    assert_eq!(emulator.read_line().unwrap(), "S: A2 5");
+    assert_eq!(emulator.read_line().unwrap(), "S: JOY1 3 4");
+}
+
+#[tokio::test]
+pub async fn test_simulation() {
+    let emulator = EmulatedSerialPort::new()
+        .with_latency(Duration::ZERO)
+        .with_simulation(Simulation::new()
+            .with_model(Encoder::new("M1", "A2", 100.0))
+            .with_model(Light::new("M2", "A3", 50, 950))
+            .with_model(Potentiometer::new("SERVO1", "A4", 2.0, 100)));
+
+    let ftswarm = FtSwarm::new(emulator.clone());
+    let motor = Motor::create(&ftswarm, "M1", ()).await;
+    let motor = lock(&motor).await.clone();
+    let lamp = Lamp::create(&ftswarm, "M2", ()).await;
+    let lamp = lock(&lamp).await.clone();
+    let servo = Servo::create(&ftswarm, "SERVO1", ()).await;
+    let servo = lock(&servo).await.clone();
+    let encoder = RotaryEncoder::create(&ftswarm, "A2", true).await;
+    let ldr = Ldr::create(&ftswarm, "A3", Hysteresis(0)).await;
+    let potentiometer = Analog::create(&ftswarm, "A4", Hysteresis(0)).await;
+
+    motor.set(-255).await.unwrap();
+    motor.set_motion_type(MotionType::On).await.unwrap();
+    lamp.set(ValueState::High).await.unwrap();
+    lamp.set_motion_type(MotionType::On).await.unwrap();
+    servo.set_position(30).await.unwrap();
+    emulator.advance(Duration::from_millis(1500));
+    assert_eq!(emulator.now(), Duration::from_millis(1500));
+
+    let mut encoder_values = lock(&encoder).await.watch();
+    let mut ldr_values = lock(&ldr).await.watch();
+    let mut potentiometer_values = lock(&potentiometer).await.watch();
+    encoder_values.wait_for(|change| change.value == -150).await.unwrap();
+    ldr_values.wait_for(|change| change.value == 950).await.unwrap();
+    potentiometer_values.wait_for(|change| change.value == 160).await.unwrap();
+
+    // A braked motor doesn't advance the encoder
+    motor.brake().await.unwrap();
+    emulator.advance(Duration::from_secs(1));
+    assert_eq!(emulator.input("A2"), Some(-150));
+}
+
+#[tokio::test]
+pub async fn test_simulated_homing() {
+    let emulator = EmulatedSerialPort::new()
+        .with_latency(Duration::ZERO)
+        .with_simulation(Simulation::new().with_model(Slide::stepper("M1", "A1", -500)));
+
+    let ftswarm = FtSwarm::new(emulator.clone());
+    let stepper = Stepper::create(&ftswarm, "M1", ()).await;
+    let stepper = lock(&stepper).await.clone();
+    let end_switch = Switch::create(&ftswarm, "A1", NormallyOpen::Open).await;
+    let end_switch = lock(&end_switch).await.clone();
+
+    // The virtual clock runs ten times as fast as the real one while homing
+    let clock = tokio::spawn({
+        let emulator = emulator.clone();
+        async move {
+            loop {
+                emulator.advance(Duration::from_millis(10));
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        }
+    });
+
+    // Too slow to reach the switch in time
+    let error = stepper.home(end_switch.changes(), 100, Direction::Backward, Duration::from_millis(100)).await.unwrap_err();
+    assert!(matches!(error, FtSwarmError::HomingFailed { .. }));
+    assert_eq!(emulator.input("A1"), Some(0));
+
+    stepper.home(end_switch.changes(), 1000, Direction::Backward, Duration::from_secs(5)).await.unwrap();
+    clock.abort();
+    assert_eq!(stepper.get_position().await.unwrap(), 0);
+    assert_eq!(emulator.input("A1"), Some(1));
+
+    // A braked stepper holds its position
+    emulator.advance(Duration::from_secs(1));
+    assert_eq!(stepper.get_position().await.unwrap(), 0);
+
+    stepper.set_speed(200, Direction::Forward).await.unwrap();
+    stepper.set_motion_type(MotionType::On).await.unwrap();
+    emulator.advance(Duration::from_secs(1));
+    assert_eq!(stepper.get_position().await.unwrap(), 200);
+    assert_eq!(emulator.input("A1"), Some(0));
+}
+
+/// Sends the same commands through a fault injector, returns the faults and the lines read
+fn faulty_traffic(seed: u64) -> (Vec<(usize, Fault)>, Vec<String>) {
+    let schedule = FaultSchedule::seeded(seed)
+        .drop(0.1)
+        .corrupt(0.1)
+        .truncate(0.1)
+        .spurious_log(0.1)
+        .spurious_error(0.1);
+    let mut port = FaultInjector::new(EmulatedSerialPort::new().with_latency(Duration::ZERO), schedule);
+    let log = port.log();
+
+    let mut lines = Vec::new();
+    for i in 0..100 {
+        port.write_line(format!("{}.setRegister({}, {})", HOSTNAME, i % 8, i)).unwrap();
+        port.write_line(format!("{}.getRegister({})", HOSTNAME, i % 8)).unwrap();
+        while port.available().unwrap() {
+            lines.push(port.read_line().unwrap());
+        }
+    }
+
+    (log.entries(), lines)
+}
+
+#[test]
+pub fn test_fault_schedule_is_reproducible() {
+    let (faults, lines) = faulty_traffic(7);
+    assert!(faults.len() > 20);
+    assert_eq!(faulty_traffic(7), (faults.clone(), lines));
+    assert_ne!(faulty_traffic(8).0, faults);
+}
+
+#[tokio::test]
+pub async fn test_injected_disconnect() {
+    let emulator = EmulatedSerialPort::new().with_latency(Duration::ZERO);
+    let connections = Arc::new(AtomicU64::new(0));
+    let policy = ReconnectPolicy {
+        initial_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(1),
+        max_attempts: Some(3),
+    };
+
+    let connect = {
+        let emulator = emulator.clone();
+        let connections = connections.clone();
+        move || {
+            // The first connection drops after a few lines
+            let connection = connections.fetch_add(1, Ordering::SeqCst);
+            let schedule = match connection {
+                0 => FaultSchedule::seeded(connection).at(4, Fault::Disconnect),
+                _ => FaultSchedule::seeded(connection),
+            };
+            let port = BlockingAdapter::new(FaultInjector::new(emulator.clone(), schedule));
+            async move { Ok(port) }
+        }
+    };
+    let ftswarm = FtSwarm::connect_with_reconnect(connect, policy).await.unwrap();
+    let controller = Controller::create(&ftswarm, HOSTNAME, ()).await;
+    let controller = lock(&controller).await.clone();
+
+    for register in 0..8 {
+        while controller.set_register(register, register as u32 * 10).await.is_err() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    for register in 0..8 {
+        assert_eq!(controller.get_register(register).await.unwrap(), register as u32 * 10);
+    }
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+    assert_eq!(*ftswarm.connection_state().borrow(), ConnectionState::Connected);
+}
+
+#[tokio::test]
+pub async fn test_serve_tcp() {
+    let emulator = EmulatedSerialPort::new().with_latency(Duration::ZERO);
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    {
+        let emulator = emulator.clone();
+        std::thread::spawn(move || server::serve_tcp(emulator, listener));
+    }
+
+    let stream = tokio::net::TcpStream::connect(address).await.unwrap();
+    let ftswarm = FtSwarm::new_async(AsyncLinePort::new(stream)).await.unwrap();
+    assert_eq!(ftswarm.whoami().await.unwrap().hostname, HOSTNAME);
+
+    let switch = Switch::create(&ftswarm, "A1", NormallyOpen::Open).await;
+    let mut pressed = lock(&switch).await.watch();
+    emulator.set_input("A1", 1);
+    pressed.wait_for(|change| change.value).await.unwrap();
+
+    // Lines the firmware can't parse are answered with an error
+    let error = ftswarm.transact(FtSwarmCommand::Direct(FtSwarmDirectCommand::Custom("A1.getValue(".to_string()))).await;
+    assert!(matches!(error, Err(FtSwarmError::Firmware(_))));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+pub async fn test_serve_pty() {
+    let emulator = EmulatedSerialPort::new().with_latency(Duration::ZERO);
+    let pty = server::Pty::open().unwrap();
+    let path = pty.path().to_str().unwrap().to_string();
+    std::thread::spawn(move || pty.serve(emulator));
+
+    // Through the same code as a USB connected ftSwarm
+    let ftswarm = FtSwarm::new(SerialCommunication::open(&path).unwrap());
+    let controller = Controller::create(&ftswarm, HOSTNAME, ()).await;
+    let controller = lock(&controller).await.clone();
+    controller.set_register(1, 42).await.unwrap();
+    assert_eq!(controller.get_register(1).await.unwrap(), 42);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+pub async fn test_discovery() {
+    let pty = server::Pty::open().unwrap();
+    let path = pty.path().to_str().unwrap().to_string();
+    std::thread::spawn(move || pty.serve(EmulatedSerialPort::new().with_latency(Duration::ZERO)));
+
+    // A device that never answers
+    let silent = nix::pty::openpty(None, None).unwrap();
+    let silent_path = nix::unistd::ttyname(&silent.slave).unwrap().to_str().unwrap().to_string();
+
+    let discovery = Discovery::new().with_timeout(Duration::from_millis(300));
+    let found = discovery.probe_ports([path.clone(), silent_path, "/dev/ftswarm-missing".to_string()]).await;
+    assert_eq!(found, vec![DiscoveredSwarm {
+        port: path.clone(),
+        whoami: WhoamiResponse {
+            hostname: HOSTNAME.to_string(),
+            id: "ftSwarm100".to_string(),
+            serial: Some(100),
+        },
+    }]);
+
+    // The probed port was closed again
+    let serial = AsyncSerialCommunication::open(&path).unwrap();
+    let (_, whoami) = ftswarm::discovery::probe(serial, Duration::from_secs(1)).await.unwrap();
+    assert_eq!(whoami.hostname, HOSTNAME);
 }
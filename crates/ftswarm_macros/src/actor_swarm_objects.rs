@@ -29,7 +29,7 @@ pub fn actor_swarm_object_impl(input: TokenStream) -> TokenStream {
     let impl_block = if digital {
         quote! {
             impl #typename {
-                pub async fn set(&self, value: ValueState) -> Result<(), String> {
+                pub async fn set(&self, value: ValueState) -> Result<(), FtSwarmError> {
                     self.run_command(
                         RpcFunction::SetSpeed,
                         vec![Argument::Int(value.into())]
@@ -40,7 +40,7 @@ pub fn actor_swarm_object_impl(input: TokenStream) -> TokenStream {
     } else {
         quote! {
             impl #typename {
-                pub async fn set(&self, value: i32) -> Result<(), String> {
+                pub async fn set(&self, value: i32) -> Result<(), FtSwarmError> {
                     let value = value.max(-255).min(255);
 
                     self.run_command(
@@ -52,7 +52,7 @@ pub fn actor_swarm_object_impl(input: TokenStream) -> TokenStream {
         }
     };
 
-    return quote! {
+    quote! {
         #[derive(Clone, Updateable)]
         pub struct #typename {
             pub name: String,
@@ -71,16 +71,57 @@ pub fn actor_swarm_object_impl(input: TokenStream) -> TokenStream {
                 })
             }
 
-            fn init(&mut self) -> impl Future<Output = ()> {
+            fn init(&mut self) -> impl Future<Output = Result<(), FtSwarmError>> {
                 async move {
-                    self.run_command(
+                    self.run_setup_command(
                         RpcFunction::SetActorType,
                         vec![Argument::ActorType(ActorType::#typename)]
-                    ).await.unwrap();
+                    ).await?;
+
+                    Ok(())
                 }
             }
         }
 
+        impl #typename {
+            /// Read back the actor type the port is configured as
+            pub async fn get_actor_type(&self) -> Result<ActorType, FtSwarmError> {
+                get_typed(self, RpcFunction::GetActorType).await
+            }
+
+            /// The speed the actor was last set to, from -255 to 255
+            pub async fn get_speed(&self) -> Result<i32, FtSwarmError> {
+                self.run_command(RpcFunction::GetSpeed, vec![])
+                    .await
+                    .and_then(|response| response.as_int().ok_or_else(|| FtSwarmError::unexpected("Invalid speed")))
+            }
+
+            /// Switch the actor on, or stop it by coasting or braking. It is restored after a reconnect
+            pub async fn set_motion_type(&self, motion_type: MotionType) -> Result<(), FtSwarmError> {
+                run_recorded_command(self, RpcFunction::SetMotionType, vec![Argument::MotionType(motion_type)]).await
+            }
+
+            pub async fn get_motion_type(&self) -> Result<MotionType, FtSwarmError> {
+                get_typed(self, RpcFunction::GetMotionType).await
+            }
+
+            /// Stop with the outputs shorted, so the motor stops right away
+            pub async fn brake(&self) -> Result<(), FtSwarmError> {
+                self.set_motion_type(MotionType::Brake).await
+            }
+
+            /// Stop with the outputs open, so the motor runs out freely
+            pub async fn coast(&self) -> Result<(), FtSwarmError> {
+                self.set_motion_type(MotionType::Coast).await
+            }
+        }
+
+        impl TriggerTarget for #typename {
+            fn target_name(&self) -> &str {
+                &self.name
+            }
+        }
+
         #impl_block
     }.into()
 }
\ No newline at end of file
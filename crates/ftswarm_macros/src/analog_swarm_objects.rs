@@ -18,12 +18,13 @@ pub fn analog_swarm_object_impl(input: TokenStream) -> TokenStream {
     let parsed: AnalogSwarmObjectParsed = syn::parse(input).unwrap();
     let typename = parsed.typename;
 
-    return quote! {
+    quote! {
         #[derive(Clone)]
         pub struct #typename {
             pub name: String,
             pub hysteresis: Hysteresis,
             pub value: i32,
+            values: ValueWatch<i32>,
             swarm: FtSwarm
         }
 
@@ -38,28 +39,34 @@ pub fn analog_swarm_object_impl(input: TokenStream) -> TokenStream {
                     name: name.to_string(),
                     hysteresis,
                     value: 0,
+                    values: ValueWatch::new(0),
                     swarm
                 })
             }
 
-            fn init(&mut self) -> impl Future<Output = ()> {
+            fn init(&mut self) -> impl Future<Output = Result<(), FtSwarmError>> {
                 async move {
-                    self.run_command(
+                    self.run_setup_command(
                         RpcFunction::SetSensorType,
                         vec![Argument::SensorType(SensorType::#typename), NormallyOpen::Open.into()]
-                    ).await.unwrap();
+                    ).await?;
 
-                    self.run_command(
+                    self.run_setup_command(
                         RpcFunction::Subscribe,
                         vec![Argument::Int(self.hysteresis.0.clone() as i64)]
-                    ).await.unwrap();
+                    ).await?;
 
                     self.value = self.run_command(RpcFunction::GetValue, vec![])
                         .await.ok()
                         .and_then(|param| param.as_int())
                         .unwrap_or(0);
+                    self.values.update(self.value);
+
+                    Ok(())
                 }
             }
         }
+
+        impl_input_object!(#typename);
     }.into()
 }
\ No newline at end of file
@@ -27,10 +27,10 @@ pub fn digital_swarm_object_impl(input: TokenStream) -> TokenStream {
     let toggle_type = if has_toggle {
         quote! {
             impl #typename {
-                pub async fn get_toggle(&self) -> Result<ToggleType, String> {
+                pub async fn get_toggle(&self) -> Result<ToggleType, FtSwarmError> {
                     return self.run_command(RpcFunction::GetToggle, vec![])
                         .await
-                        .and_then(|param| param.as_int().ok_or("Invalid toggle value".to_string()))
+                        .and_then(|param| param.as_int().ok_or_else(|| FtSwarmError::unexpected("Invalid toggle value")))
                         .and_then(|param| Ok(ToggleType::from(param)))
                 }
             }
@@ -39,11 +39,12 @@ pub fn digital_swarm_object_impl(input: TokenStream) -> TokenStream {
         quote! {}
     };
 
-    return quote! {
+    quote! {
         #[derive(Clone)]
         pub struct #typename {
             pub name: String,
             pub value: bool,
+            values: ValueWatch<bool>,
             normally_open: NormallyOpen,
             swarm: FtSwarm,
         }
@@ -58,31 +59,36 @@ pub fn digital_swarm_object_impl(input: TokenStream) -> TokenStream {
                 Box::new(#typename {
                     name: name.to_string(),
                     value: false,
+                    values: ValueWatch::new(false),
                     normally_open,
                     swarm,
                 })
             }
 
-            fn init(&mut self) -> impl Future<Output=()> {
+            fn init(&mut self) -> impl Future<Output=Result<(), FtSwarmError>> {
                 async move {
-                    self.run_command(
+                    self.run_setup_command(
                         RpcFunction::SetSensorType,
                         vec![Argument::SensorType(SensorType::#typename), self.normally_open.clone().into()],
-                    ).await.unwrap();
+                    ).await?;
 
-                    self.run_command(
+                    self.run_setup_command(
                         RpcFunction::Subscribe,
                         vec![Argument::Int(0i64)],
-                    ).await.unwrap();
+                    ).await?;
 
                     self.value = self.run_command(RpcFunction::GetValue, vec![])
                         .await.ok()
                         .and_then(|param| param.as_int())
                         .unwrap_or(0) == 1;
+                    self.values.update(self.value);
+
+                    Ok(())
                 }
             }
         }
 
         #toggle_type
+        impl_input_object!(#typename);
     }.into()
 }
\ No newline at end of file
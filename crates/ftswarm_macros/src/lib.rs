@@ -37,6 +37,7 @@ pub fn impl_int_updateable(src: TokenStream) -> TokenStream {
             fn handle_subscription(&mut self, message: &RPCReturnParam) {
                 if let RPCReturnParam::Int(value) = message {
                     self.value = *value;
+                    self.values.update(self.value);
                 }
             }
         }
@@ -45,6 +46,16 @@ pub fn impl_int_updateable(src: TokenStream) -> TokenStream {
             pub async fn get_value(&self) -> i32 {
                 self.value
             }
+
+            /// Follow the value without locking this object
+            pub fn watch(&self) -> tokio::sync::watch::Receiver<ValueChange<i32>> {
+                self.values.watch()
+            }
+
+            /// The current value, then every change
+            pub fn changes(&self) -> impl Stream<Item=ValueChange<i32>> {
+                self.values.changes()
+            }
         }
     };
     gen.into()
@@ -58,6 +69,7 @@ pub fn impl_bool_updateable(src: TokenStream) -> TokenStream {
             fn handle_subscription(&mut self, message: &RPCReturnParam) {
                 if let RPCReturnParam::Int(value) = message {
                     self.value = *value == 1;
+                    self.values.update(self.value);
                 }
             }
         }
@@ -66,6 +78,42 @@ pub fn impl_bool_updateable(src: TokenStream) -> TokenStream {
             pub async fn get_value(&self) -> bool {
                 self.value
             }
+
+            /// Follow the value without locking this object
+            pub fn watch(&self) -> tokio::sync::watch::Receiver<ValueChange<bool>> {
+                self.values.watch()
+            }
+
+            /// The current value, then every change
+            pub fn changes(&self) -> impl Stream<Item=ValueChange<bool>> {
+                self.values.changes()
+            }
+        }
+    };
+    gen.into()
+}
+
+/// This macro generates the methods every input shares: triggers and reading back the sensor type.
+#[proc_macro]
+pub fn impl_input_object(src: TokenStream) -> TokenStream {
+    let ast: Ident = syn::parse(src).unwrap();
+    let gen = quote! {
+        impl #ast {
+            /// Let the controller set `actor` to `value` when `event` occurs on this input,
+            /// without a round trip through the host
+            pub async fn on_trigger(&self, event: TriggerEvent, actor: &impl TriggerTarget, value: i32) -> Result<(), FtSwarmError> {
+                set_trigger(self, RpcFunction::OnTrigger, event, actor, Some(value)).await
+            }
+
+            /// Like `on_trigger`, but `actor` is set to the value of this input
+            pub async fn on_trigger_forward(&self, event: TriggerEvent, actor: &impl TriggerTarget) -> Result<(), FtSwarmError> {
+                set_trigger(self, RpcFunction::OnTrigger, event, actor, None).await
+            }
+
+            /// Read back the sensor type the port is configured as
+            pub async fn get_sensor_type(&self) -> Result<SensorType, FtSwarmError> {
+                get_typed(self, RpcFunction::GetSensorType).await
+            }
         }
     };
     gen.into()
@@ -103,7 +151,7 @@ pub fn impl_swarm_object(src: TokenStream) -> TokenStream {
 /// When using the `default_new_swarm_object_impls` macro, the `name` and `swarm` fields are automatically implemented.
 #[proc_macro]
 pub fn default_new_swarm_object_impls(_: TokenStream) -> TokenStream {
-    return quote! {
+    quote! {
         fn name(&self) -> &str {
             &self.name
         }
@@ -111,7 +159,7 @@ pub fn default_new_swarm_object_impls(_: TokenStream) -> TokenStream {
         fn swarm(&self) -> &FtSwarm {
             &self.swarm
         }
-    }.into();
+    }.into()
 }
 
 #[proc_macro]
@@ -10,10 +10,7 @@ fn main() {
     ];
 
     for message in messages {
-        match S2RMessage::try_from(message.to_string()) {
-            Ok(msg) => println!("Message: {:?}", msg),
-            Err(e) => println!("Error: {}", e),
-        }
+        println!("Message: {:?}", S2RMessage::from(message.to_string()));
     }
 
     let return_values = vec![
@@ -23,9 +20,6 @@ fn main() {
     ];
 
     for value in return_values {
-        match RPCReturnParam::try_from(value.to_string()) {
-            Ok(msg) => println!("Param: {:?}", msg),
-            Err(e) => println!("Error: {}", e),
-        }
+        println!("Param: {:?}", RPCReturnParam::from(value.to_string()));
     }
 }
\ No newline at end of file
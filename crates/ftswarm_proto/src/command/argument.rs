@@ -1,7 +1,9 @@
 use crate::{Deserialized, IdOf, Serialized};
-use crate::command::enums::{ActorType, MicroStepMode, MotionType, SensorType};
+use crate::error::ProtoError;
+use crate::command::parser;
+use crate::command::enums::{ActorType, MicroStepMode, MotionType, SensorType, TriggerEvent};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Argument {
     Int(i64),
     Float(f64),
@@ -9,25 +11,73 @@ pub enum Argument {
     ActorType(ActorType),
     SensorType(SensorType),
     MotionType(MotionType),
-    MicroStepMode(MicroStepMode)
+    MicroStepMode(MicroStepMode),
+    TriggerEvent(TriggerEvent),
+    /// A reference to another port by its name, e.g. the actor of an `onTrigger` action
+    Actor(String),
+    /// A quoted string, e.g. the text of a `show` command
+    String(String),
+}
+
+/// The kind of an argument a function expects, see [`RpcFunction::signature`](crate::command::rpc::RpcFunction::signature)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgumentKind {
+    Int,
+    Float,
+    Bool,
+    ActorType,
+    SensorType,
+    MotionType,
+    MicroStepMode,
+    TriggerEvent,
+    Actor,
+    String,
+}
+
+impl ArgumentKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArgumentKind::Int => "an integer",
+            ArgumentKind::Float => "a number",
+            ArgumentKind::Bool => "a boolean",
+            ArgumentKind::ActorType => "an actor type",
+            ArgumentKind::SensorType => "a sensor type",
+            ArgumentKind::MotionType => "a motion type",
+            ArgumentKind::MicroStepMode => "a micro step mode",
+            ArgumentKind::TriggerEvent => "a trigger event",
+            ArgumentKind::Actor => "a port name",
+            ArgumentKind::String => "a string",
+        }
+    }
 }
 
 impl Serialized for Argument {
     fn serialize(&self) -> String {
         match self {
             Argument::Int(i) => i.to_string(),
-            Argument::Float(f) => f.to_string(),
-            Argument::Bool(b) => (if b.clone() { 1 } else { 0 }).to_string(),
+            // Debug keeps the decimal point, so the value is read back as a float
+            Argument::Float(f) => format!("{:?}", f),
+            Argument::Bool(b) => (if *b { 1 } else { 0 }).to_string(),
             Argument::ActorType(a) => a.id().to_string(),
             Argument::SensorType(s) => s.id().to_string(),
             Argument::MotionType(m) => m.id().to_string(),
-            Argument::MicroStepMode(m) => m.id().to_string()
+            Argument::MicroStepMode(m) => m.id().to_string(),
+            Argument::TriggerEvent(t) => t.id().to_string(),
+            Argument::Actor(name) => name.clone(),
+            Argument::String(value) => {
+                let escaped = value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n")
+                    .replace('\t', "\\t");
+                format!("\"{}\"", escaped)
+            },
         }
     }
 }
 
 impl Deserialized for Argument {
-    fn deserialize(value: &String) -> Result<Self, String> where Self: Sized {
-        Ok(Argument::Int(value.parse::<i64>().map_err(|_| format!("Error parsing int at {}", value))?))
+    fn deserialize(value: &str) -> Result<Self, ProtoError> where Self: Sized {
+        parser::parse_argument(value)
     }
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 use crate::{Deserialized, NameOf, Serialized};
+use crate::error::ProtoError;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum FtSwarmDirectCommand {
     Help,
     Setup,
@@ -32,15 +33,15 @@ impl Serialized for FtSwarmDirectCommand {
 }
 
 impl Deserialized for FtSwarmDirectCommand {
-    fn deserialize(value: &String) -> Result<Self, String> where Self: Sized {
-        match value.as_str() {
+    fn deserialize(value: &str) -> Result<Self, ProtoError> where Self: Sized {
+        match value {
             "help" => Ok(FtSwarmDirectCommand::Help),
             "setup" => Ok(FtSwarmDirectCommand::Setup),
             "halt" => Ok(FtSwarmDirectCommand::Halt),
             "whoami" => Ok(FtSwarmDirectCommand::Whoami),
             "uptime" => Ok(FtSwarmDirectCommand::Uptime),
             "startCLI" => Ok(FtSwarmDirectCommand::StartCli),
-            _ => Err(format!("Unknown command: {}", value))
+            _ => Err(ProtoError::UnknownCommand(value.to_string()))
         }
     }
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
+use strum_macros::EnumIter;
 use crate::IdOf;
+use crate::error::ProtoError;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
 pub enum SensorType {
     Digital,
     Analog,
@@ -43,7 +45,33 @@ impl IdOf for SensorType {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl TryFrom<i32> for SensorType {
+    type Error = ProtoError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(SensorType::Digital),
+            1 => Ok(SensorType::Analog),
+            2 => Ok(SensorType::Switch),
+            3 => Ok(SensorType::ReedSwitch),
+            4 => Ok(SensorType::LightBarrier),
+            5 => Ok(SensorType::Voltmeter),
+            6 => Ok(SensorType::Ohmmeter),
+            7 => Ok(SensorType::Thermometer),
+            8 => Ok(SensorType::Ldr),
+            9 => Ok(SensorType::TrailSensor),
+            10 => Ok(SensorType::ColorSensor),
+            11 => Ok(SensorType::Ultrasonic),
+            12 => Ok(SensorType::CamSensor),
+            13 => Ok(SensorType::Counter),
+            14 => Ok(SensorType::RotaryEncoder),
+            15 => Ok(SensorType::FrequencyMeter),
+            _ => Err(ProtoError::UnknownId { kind: "sensor type", id: value }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
 pub enum ActorType {
     Motor,
     XMMotor,
@@ -72,7 +100,26 @@ impl IdOf for ActorType {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl TryFrom<i32> for ActorType {
+    type Error = ProtoError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ActorType::Motor),
+            1 => Ok(ActorType::XMMotor),
+            2 => Ok(ActorType::Tractor),
+            3 => Ok(ActorType::Encoder),
+            4 => Ok(ActorType::Lamp),
+            5 => Ok(ActorType::Valve),
+            6 => Ok(ActorType::Compressor),
+            7 => Ok(ActorType::Buzzer),
+            8 => Ok(ActorType::Stepper),
+            _ => Err(ProtoError::UnknownId { kind: "actor type", id: value }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
 pub enum MotionType {
     Coast,
     Brake,
@@ -89,6 +136,19 @@ impl IdOf for MotionType {
     }
 }
 
+impl TryFrom<i32> for MotionType {
+    type Error = ProtoError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(MotionType::Coast),
+            1 => Ok(MotionType::Brake),
+            2 => Ok(MotionType::On),
+            _ => Err(ProtoError::UnknownId { kind: "motion type", id: value }),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ToggleType {
     None,
@@ -116,7 +176,7 @@ impl From<i32> for ToggleType {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
 pub enum MicroStepMode {
     FullStep,
     HalfStep,
@@ -135,4 +195,127 @@ impl IdOf for MicroStepMode {
             MicroStepMode::SixteenthStep => 4,
         }
     }
-}
\ No newline at end of file
+}
+
+impl TryFrom<i32> for MicroStepMode {
+    type Error = ProtoError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(MicroStepMode::FullStep),
+            1 => Ok(MicroStepMode::HalfStep),
+            2 => Ok(MicroStepMode::QuarterStep),
+            3 => Ok(MicroStepMode::EighthStep),
+            4 => Ok(MicroStepMode::SixteenthStep),
+            _ => Err(ProtoError::UnknownId { kind: "micro step mode", id: value }),
+        }
+    }
+}
+
+/// When an input fires an `onTrigger` action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum TriggerEvent {
+    /// The value rises above the trigger level, e.g. a switch is pressed
+    Up,
+    /// The value falls below the trigger level, e.g. a switch is released
+    Down,
+    /// The value changes
+    Value,
+}
+
+impl IdOf for TriggerEvent {
+    fn id(&self) -> u32 {
+        match self {
+            TriggerEvent::Up => 0,
+            TriggerEvent::Down => 1,
+            TriggerEvent::Value => 2,
+        }
+    }
+}
+
+impl TryFrom<i32> for TriggerEvent {
+    type Error = ProtoError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(TriggerEvent::Up),
+            1 => Ok(TriggerEvent::Down),
+            2 => Ok(TriggerEvent::Value),
+            _ => Err(ProtoError::UnknownId { kind: "trigger event", id: value }),
+        }
+    }
+}
+
+/// The kind of IO behind a port, as reported by `getIOType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum IOType {
+    /// A generic input
+    Input,
+    /// A digital input
+    DigitalInput,
+    /// An analog input
+    AnalogInput,
+    /// A motor output
+    Actor,
+    /// A button on the controller
+    Button,
+    /// A joystick on the ftSwarmRS
+    Joystick,
+    /// An RGB LED
+    Led,
+    /// A servo output
+    Servo,
+    /// The display of the ftSwarmControl
+    Oled,
+    /// The gyro of the ftSwarmXL
+    Gyro,
+    /// The shift register input of the ftSwarmControl
+    Hc165,
+    /// The I2C slave registers
+    I2c,
+    /// The camera of the ftSwarmCAM
+    Cam,
+}
+
+impl IdOf for IOType {
+    fn id(&self) -> u32 {
+        match self {
+            IOType::Input => 0,
+            IOType::DigitalInput => 1,
+            IOType::AnalogInput => 2,
+            IOType::Actor => 3,
+            IOType::Button => 4,
+            IOType::Joystick => 5,
+            IOType::Led => 6,
+            IOType::Servo => 7,
+            IOType::Oled => 8,
+            IOType::Gyro => 9,
+            IOType::Hc165 => 10,
+            IOType::I2c => 11,
+            IOType::Cam => 12,
+        }
+    }
+}
+
+impl TryFrom<i32> for IOType {
+    type Error = ProtoError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(IOType::Input),
+            1 => Ok(IOType::DigitalInput),
+            2 => Ok(IOType::AnalogInput),
+            3 => Ok(IOType::Actor),
+            4 => Ok(IOType::Button),
+            5 => Ok(IOType::Joystick),
+            6 => Ok(IOType::Led),
+            7 => Ok(IOType::Servo),
+            8 => Ok(IOType::Oled),
+            9 => Ok(IOType::Gyro),
+            10 => Ok(IOType::Hc165),
+            11 => Ok(IOType::I2c),
+            12 => Ok(IOType::Cam),
+            _ => Err(ProtoError::UnknownId { kind: "IO type", id: value }),
+        }
+    }
+}
@@ -1,13 +1,15 @@
 use crate::command::direct::FtSwarmDirectCommand;
 use crate::command::rpc::FtSwarmRPCCommand;
 use crate::{Deserialized, Serialized};
+use crate::error::ProtoError;
 
 pub mod enums;
 pub mod direct;
 pub mod rpc;
 pub mod argument;
+pub mod parser;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum FtSwarmCommand {
     RPC(FtSwarmRPCCommand),
     Direct(FtSwarmDirectCommand),
@@ -23,8 +25,8 @@ impl Serialized for FtSwarmCommand {
 }
 
 impl Deserialized for FtSwarmCommand {
-    fn deserialize(s: &String) -> Result<FtSwarmCommand, String> {
-        if s.contains(".") {
+    fn deserialize(s: &str) -> Result<FtSwarmCommand, ProtoError> {
+        if s.contains('(') {
             Ok(FtSwarmCommand::RPC(FtSwarmRPCCommand::deserialize(s)?))
         } else {
             Ok(FtSwarmCommand::Direct(FtSwarmDirectCommand::deserialize(s)?))
@@ -0,0 +1,287 @@
+//! Tokenizer and parser for commands as they are sent to the ftSwarm
+//!
+//! ```text
+//! command   = target "." function "(" [ argument { "," argument } ] ")"
+//! target    = name, may contain dots, e.g. `kelda.A1`
+//! argument  = int | float | string | name
+//! int       = [ "-" | "+" ] digits           (whitespace after the sign is allowed)
+//! float     = int "." digits [ exponent ] | int exponent
+//! string    = '"' { char | '\"' | '\\' | '\n' | '\t' } '"'
+//! name      = ( letter | "_" ) { letter | digit | "_" | "-" | "." }
+//! ```
+//!
+//! Typed arguments like an `ActorType` or a `Bool` are sent as plain numbers, so they are
+//! recovered from the signature of the function, see [`RpcFunction::signature`].
+
+use crate::command::argument::{Argument, ArgumentKind};
+use crate::command::enums::{ActorType, MicroStepMode, MotionType, SensorType, TriggerEvent};
+use crate::command::rpc::{FtSwarmRPCCommand, RpcFunction};
+use crate::error::ProtoError;
+use crate::Deserialized;
+
+/// A single argument as it appears in the command, before its type is known
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Name(String),
+}
+
+struct Cursor<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(input: &'a str) -> Self {
+        Cursor { input, position: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.position;
+        while self.peek().is_some_and(&predicate) {
+            self.bump();
+        }
+        &self.input[start..self.position]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.position >= self.input.len()
+    }
+
+    fn error(&self, message: impl Into<String>) -> ProtoError {
+        self.error_at(self.position, message)
+    }
+
+    fn error_at(&self, position: usize, message: impl Into<String>) -> ProtoError {
+        ProtoError::Syntax { position, message: message.into() }
+    }
+
+    fn literal(&mut self) -> Result<Literal, ProtoError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"') => self.string(),
+            Some(c) if c == '-' || c == '+' || c.is_ascii_digit() => self.number(),
+            Some(c) if c.is_alphabetic() || c == '_' => Ok(Literal::Name(self.name().to_string())),
+            Some(c) => Err(self.error(format!("Unexpected '{}'", c))),
+            None => Err(self.error("Expected an argument")),
+        }
+    }
+
+    fn name(&mut self) -> &'a str {
+        self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+    }
+
+    fn string(&mut self) -> Result<Literal, ProtoError> {
+        let start = self.position;
+        self.bump();
+
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(Literal::Str(value)),
+                Some('\\') => match self.bump() {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(c) => return Err(self.error_at(self.position - c.len_utf8(), format!("Unknown escape '\\{}'", c))),
+                    None => return Err(self.error_at(start, "Unterminated string")),
+                },
+                Some(c) => value.push(c),
+                None => return Err(self.error_at(start, "Unterminated string")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Literal, ProtoError> {
+        let start = self.position;
+        let mut text = String::new();
+
+        if let Some(sign) = self.peek().filter(|c| *c == '-' || *c == '+') {
+            self.bump();
+            self.skip_whitespace();
+            if sign == '-' {
+                text.push('-');
+            }
+        }
+
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        if digits.is_empty() {
+            return Err(self.error("Expected a digit"));
+        }
+        text.push_str(digits);
+
+        let mut is_float = false;
+        if self.peek() == Some('.') {
+            self.bump();
+            let fraction = self.take_while(|c| c.is_ascii_digit());
+            if fraction.is_empty() {
+                return Err(self.error("Expected a digit after '.'"));
+            }
+            text.push('.');
+            text.push_str(fraction);
+            is_float = true;
+        }
+
+        if let Some(e) = self.peek().filter(|c| *c == 'e' || *c == 'E') {
+            self.bump();
+            text.push(e);
+            if let Some(sign) = self.peek().filter(|c| *c == '-' || *c == '+') {
+                self.bump();
+                text.push(sign);
+            }
+            let exponent = self.take_while(|c| c.is_ascii_digit());
+            if exponent.is_empty() {
+                return Err(self.error("Expected a digit in the exponent"));
+            }
+            text.push_str(exponent);
+            is_float = true;
+        }
+
+        if is_float {
+            text.parse::<f64>()
+                .map(Literal::Float)
+                .map_err(|err| self.error_at(start, format!("Invalid number {}: {}", text, err)))
+        } else {
+            text.parse::<i64>()
+                .map(Literal::Int)
+                .map_err(|err| self.error_at(start, format!("Invalid number {}: {}", text, err)))
+        }
+    }
+}
+
+/// Parse a single argument, without a function signature
+pub fn parse_argument(value: &str) -> Result<Argument, ProtoError> {
+    let mut cursor = Cursor::new(value);
+    let literal = cursor.literal()?;
+    cursor.skip_whitespace();
+    if !cursor.is_at_end() {
+        return Err(cursor.error("Unexpected input after the argument"));
+    }
+
+    Ok(untyped(literal))
+}
+
+/// Parse an rpc command like `kelda.A1.setSensorType(2, 1)`
+pub fn parse_rpc(value: &str) -> Result<FtSwarmRPCCommand, ProtoError> {
+    let mut cursor = Cursor::new(value);
+    cursor.skip_whitespace();
+
+    let start = cursor.position;
+    let callee = cursor.take_while(|c| c != '(' && !c.is_whitespace());
+    let (target, function) = callee.rsplit_once('.')
+        .ok_or_else(|| cursor.error_at(start, "Expected target.function"))?;
+    if target.is_empty() || target.split('.').any(str::is_empty) {
+        return Err(cursor.error_at(start, "Expected a target"));
+    }
+    if function.is_empty() {
+        return Err(cursor.error_at(start + target.len() + 1, "Expected a function"));
+    }
+    let function = RpcFunction::deserialize(function)?;
+
+    cursor.skip_whitespace();
+    if !cursor.eat('(') {
+        return Err(cursor.error("Expected '('"));
+    }
+
+    let mut literals = Vec::new();
+    cursor.skip_whitespace();
+    if !cursor.eat(')') {
+        loop {
+            cursor.skip_whitespace();
+            let position = cursor.position;
+            literals.push((cursor.literal()?, position));
+
+            cursor.skip_whitespace();
+            if cursor.eat(')') {
+                break;
+            }
+            if !cursor.eat(',') {
+                return Err(cursor.error("Expected ',' or ')'"));
+            }
+        }
+    }
+
+    cursor.skip_whitespace();
+    if !cursor.is_at_end() {
+        return Err(cursor.error("Unexpected input after ')'"));
+    }
+
+    let signature = function.signature();
+    let args = literals.into_iter().enumerate()
+        .map(|(index, (literal, position))| match signature.get(index) {
+            Some(kind) => typed(*kind, literal, position, &cursor),
+            None => Ok(untyped(literal)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(FtSwarmRPCCommand {
+        target: target.to_string(),
+        function,
+        args,
+    })
+}
+
+fn untyped(literal: Literal) -> Argument {
+    match literal {
+        Literal::Int(value) => Argument::Int(value),
+        Literal::Float(value) => Argument::Float(value),
+        Literal::Str(value) => Argument::String(value),
+        Literal::Name(name) if name == "true" => Argument::Bool(true),
+        Literal::Name(name) if name == "false" => Argument::Bool(false),
+        Literal::Name(name) => Argument::Actor(name),
+    }
+}
+
+fn typed(kind: ArgumentKind, literal: Literal, position: usize, cursor: &Cursor) -> Result<Argument, ProtoError> {
+    let mismatch = |literal: &Literal| cursor.error_at(position, format!("Expected {}, got {:?}", kind.name(), literal));
+
+    fn by_id<T: TryFrom<i32>>(id: i64) -> Option<T> {
+        i32::try_from(id).ok().and_then(|id| T::try_from(id).ok())
+    }
+
+    let argument = match (kind, &literal) {
+        (ArgumentKind::Int, Literal::Int(value)) => Some(Argument::Int(*value)),
+        (ArgumentKind::Float, Literal::Float(value)) => Some(Argument::Float(*value)),
+        (ArgumentKind::Float, Literal::Int(value)) => Some(Argument::Float(*value as f64)),
+        (ArgumentKind::Bool, Literal::Int(0)) => Some(Argument::Bool(false)),
+        (ArgumentKind::Bool, Literal::Int(1)) => Some(Argument::Bool(true)),
+        (ArgumentKind::Bool, Literal::Name(name)) if name == "false" => Some(Argument::Bool(false)),
+        (ArgumentKind::Bool, Literal::Name(name)) if name == "true" => Some(Argument::Bool(true)),
+        (ArgumentKind::ActorType, Literal::Int(id)) => by_id::<ActorType>(*id).map(Argument::ActorType),
+        (ArgumentKind::SensorType, Literal::Int(id)) => by_id::<SensorType>(*id).map(Argument::SensorType),
+        (ArgumentKind::MotionType, Literal::Int(id)) => by_id::<MotionType>(*id).map(Argument::MotionType),
+        (ArgumentKind::MicroStepMode, Literal::Int(id)) => by_id::<MicroStepMode>(*id).map(Argument::MicroStepMode),
+        (ArgumentKind::TriggerEvent, Literal::Int(id)) => by_id::<TriggerEvent>(*id).map(Argument::TriggerEvent),
+        (ArgumentKind::Actor, Literal::Name(name)) => Some(Argument::Actor(name.clone())),
+        (ArgumentKind::String, Literal::Str(value)) => Some(Argument::String(value.clone())),
+        _ => None,
+    };
+
+    argument.ok_or_else(|| mismatch(&literal))
+}
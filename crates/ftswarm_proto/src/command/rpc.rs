@@ -1,5 +1,7 @@
 use crate::{Deserialized, NameOf, Serialized};
-use crate::command::argument::Argument;
+use crate::command::argument::{Argument, ArgumentKind};
+use crate::command::parser;
+use crate::error::ProtoError;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
@@ -21,6 +23,8 @@ pub enum RpcFunction {
     GetFahrenheit,
     GetToggle,
     OnTrigger,
+    OnTriggerLR,
+    OnTriggerFB,
     GetActorType,
     SetActorType,
     SetSpeed,
@@ -58,6 +62,8 @@ impl NameOf for RpcFunction {
             RpcFunction::GetFahrenheit => "getFahrenheit".to_string(),
             RpcFunction::GetToggle => "getToggle".to_string(),
             RpcFunction::OnTrigger => "onTrigger".to_string(),
+            RpcFunction::OnTriggerLR => "onTriggerLR".to_string(),
+            RpcFunction::OnTriggerFB => "onTriggerFB".to_string(),
             RpcFunction::GetActorType => "getActorType".to_string(),
             RpcFunction::SetActorType => "setActorType".to_string(),
             RpcFunction::SetSpeed => "setSpeed".to_string(),
@@ -79,19 +85,42 @@ impl NameOf for RpcFunction {
     }
 }
 
+impl RpcFunction {
+    /// Whether this sets up a firmware-side trigger (`onTrigger`, `onTriggerLR`, `onTriggerFB`)
+    pub fn is_trigger(&self) -> bool {
+        matches!(self, RpcFunction::OnTrigger | RpcFunction::OnTriggerLR | RpcFunction::OnTriggerFB)
+    }
+
+    /// The kinds of the leading arguments, as far as they can't be told from the argument itself
+    ///
+    /// Enums and booleans are sent as plain numbers, so this is what tells them apart from an
+    /// `Int` when a command is parsed. Arguments past the signature are parsed as literals.
+    pub fn signature(&self) -> &'static [ArgumentKind] {
+        match self {
+            RpcFunction::SetActorType => &[ArgumentKind::ActorType],
+            RpcFunction::SetSensorType => &[ArgumentKind::SensorType, ArgumentKind::Bool],
+            RpcFunction::SetMotionType => &[ArgumentKind::MotionType],
+            RpcFunction::SetMicroStepMode => &[ArgumentKind::MicroStepMode],
+            RpcFunction::OnTrigger | RpcFunction::OnTriggerLR | RpcFunction::OnTriggerFB =>
+                &[ArgumentKind::TriggerEvent, ArgumentKind::Actor, ArgumentKind::Int],
+            _ => &[],
+        }
+    }
+}
+
 impl Deserialized for RpcFunction {
-    fn deserialize(value: &String) -> Result<Self, String> where Self: Sized {
+    fn deserialize(value: &str) -> Result<Self, ProtoError> where Self: Sized {
         for function in RpcFunction::iter() {
             if function.name() == *value {
                 return Ok(function);
             }
         }
 
-        Err(format!("Unknown function: {}", value))
+        Err(ProtoError::UnknownFunction(value.to_string()))
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FtSwarmRPCCommand {
     pub target: String,
     pub function: RpcFunction,
@@ -116,32 +145,7 @@ impl Serialized for FtSwarmRPCCommand {
 }
 
 impl Deserialized for FtSwarmRPCCommand {
-    fn deserialize(value: &String) -> Result<Self, String> where Self: Sized {
-        let mut parts = value.split(".");
-        let target = parts.next().ok_or("Can't find target")?.to_string();
-        let function_with_args = parts.next().ok_or("Can't find function")?.to_string();
-        let mut parts = function_with_args.split("(");
-        let function = parts.next().ok_or("Can't find function")?.to_string();
-        let args_str = parts.next().ok_or("Can't find args")?.to_string();
-        let args_str = args_str.trim_end_matches(")").to_string();
-
-        let function = RpcFunction::deserialize(&function)?;
-
-        let mut args = Vec::new();
-        for arg in args_str.split(",") {
-            let arg = arg.trim();
-
-            if arg.is_empty() {
-                continue;
-            }
-
-            args.push(Argument::deserialize(&arg.to_string())?);
-        }
-
-        Ok(FtSwarmRPCCommand {
-            target,
-            function,
-            args,
-        })
+    fn deserialize(value: &str) -> Result<Self, ProtoError> where Self: Sized {
+        parser::parse_rpc(value)
     }
-}
\ No newline at end of file
+}
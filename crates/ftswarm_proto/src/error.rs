@@ -0,0 +1,85 @@
+use std::fmt::{Display, Formatter};
+use std::num::ParseIntError;
+
+/// Errors that occur while parsing or interpreting protocol messages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtoError {
+    /// A direct command that isn't known
+    UnknownCommand(String),
+    /// An rpc function that isn't known
+    UnknownFunction(String),
+    /// An argument that couldn't be parsed
+    InvalidArgument {
+        value: String,
+        source: ParseIntError,
+    },
+    /// The message doesn't have the expected structure
+    Malformed(String),
+    /// The ftSwarm answered with something other than what was expected
+    UnexpectedResponse(String),
+    /// A number that doesn't stand for any variant of an enum, e.g. an unknown micro step mode
+    UnknownId {
+        kind: &'static str,
+        id: i32,
+    },
+    /// A command that doesn't follow the grammar, `position` is the byte offset of the problem
+    Syntax {
+        position: usize,
+        message: String,
+    },
+}
+
+impl Display for ProtoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtoError::UnknownCommand(command) => write!(f, "Unknown command: {}", command),
+            ProtoError::UnknownFunction(function) => write!(f, "Unknown function: {}", function),
+            ProtoError::InvalidArgument { value, .. } => write!(f, "Error parsing argument {}", value),
+            ProtoError::Malformed(message) => write!(f, "Malformed message: {}", message),
+            ProtoError::UnexpectedResponse(message) => write!(f, "Unexpected response: {}", message),
+            ProtoError::UnknownId { kind, id } => write!(f, "Unknown {} {}", kind, id),
+            ProtoError::Syntax { position, message } => write!(f, "{} (at {})", message, position),
+        }
+    }
+}
+
+impl std::error::Error for ProtoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProtoError::InvalidArgument { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// An error reported by the ftSwarm firmware
+///
+/// The firmware points at the offending part of the command with a caret,
+/// e.g. `          ^ Error: Port not found`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareError {
+    /// The column the caret points at
+    pub column: usize,
+    pub message: String,
+}
+
+impl From<&str> for FirmwareError {
+    fn from(value: &str) -> Self {
+        let column = value.find('^').unwrap_or(0);
+        let message = value[column..].trim_start_matches('^').trim();
+        let message = message.strip_prefix("Error:").unwrap_or(message).trim();
+
+        FirmwareError {
+            column,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for FirmwareError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at column {})", self.message, self.column)
+    }
+}
+
+impl std::error::Error for FirmwareError {}
@@ -1,5 +1,8 @@
+use crate::error::ProtoError;
+
 pub mod command;
 pub mod message_parser;
+pub mod error;
 
 pub trait IdOf {
     /// Some objects have an ID, this function returns it
@@ -17,17 +20,25 @@ pub trait Serialized {
 
 pub trait Deserialized {
     /// Deserialize the string into the object
-    fn deserialize(value: &String) -> Result<Self, String> where Self: Sized;
+    fn deserialize(value: &str) -> Result<Self, ProtoError> where Self: Sized;
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::command::argument::Argument;
+    use proptest::prelude::*;
+    use strum::IntoEnumIterator;
+    use crate::command::argument::{Argument, ArgumentKind};
     use crate::command::direct::FtSwarmDirectCommand::Help;
     use crate::command::FtSwarmCommand;
-    use crate::command::rpc::FtSwarmRPCCommand;
+    use crate::command::enums::{ActorType, IOType, MicroStepMode, MotionType, SensorType, TriggerEvent};
+    use crate::command::rpc::{FtSwarmRPCCommand, RpcFunction};
     use crate::command::rpc::RpcFunction::GetResistance;
-    use crate::{Deserialized, Serialized};
+    use crate::{Deserialized, IdOf, Serialized};
+    use crate::error::ProtoError;
+    use crate::message_parser::S2RMessage;
+    use crate::message_parser::rpc::RPCReturnParam;
+    use crate::message_parser::subscription::Subscription;
+    use crate::message_parser::user_event::UserEvent;
 
     fn test_serialize<T: Serialized>(obj: T, expected: &str) {
         assert_eq!(obj.serialize(), expected);
@@ -49,7 +60,7 @@ mod tests {
 
     #[test]
     fn test_deserialize_rpc() {
-        let cmd = FtSwarmCommand::deserialize(&"hello.getResistance(42)".to_string()).unwrap();
+        let cmd = FtSwarmCommand::deserialize("hello.getResistance(42)").unwrap();
         match cmd {
             FtSwarmCommand::RPC(rpc) => {
                 assert_eq!(rpc.target, "hello");
@@ -66,10 +77,195 @@ mod tests {
 
     #[test]
     fn test_deserialize_direct() {
-        let cmd = FtSwarmCommand::deserialize(&"help".to_string()).unwrap();
+        let cmd = FtSwarmCommand::deserialize("help").unwrap();
         match cmd {
             FtSwarmCommand::Direct(Help) => {},
             _ => panic!("Expected Direct command")
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_deserialize_errors() {
+        assert_eq!(FtSwarmCommand::deserialize("jump").unwrap_err(), ProtoError::UnknownCommand("jump".to_string()));
+        assert_eq!(FtSwarmCommand::deserialize("hello.jump()").unwrap_err(), ProtoError::UnknownFunction("jump".to_string()));
+        assert!(matches!(
+            FtSwarmCommand::deserialize("hello.getValue(1.)").unwrap_err(),
+            ProtoError::Syntax { position: 17, .. }
+        ));
+        assert!(matches!(
+            FtSwarmCommand::deserialize("hello.getValue(1, 2").unwrap_err(),
+            ProtoError::Syntax { position: 19, .. }
+        ));
+        assert!(matches!(
+            FtSwarmCommand::deserialize("hello.show(\"abc)").unwrap_err(),
+            ProtoError::Syntax { position: 11, .. }
+        ));
+        assert!(matches!(
+            FtSwarmCommand::deserialize("hello.setActorType(42)").unwrap_err(),
+            ProtoError::Syntax { position: 19, .. }
+        ));
+        assert!(matches!(
+            FtSwarmCommand::deserialize("hello.getValue() x").unwrap_err(),
+            ProtoError::Syntax { position: 17, .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_firmware_error() {
+        match S2RMessage::from("    ^ Error: Port not found".to_string()) {
+            S2RMessage::Error(error) => {
+                assert_eq!(error.column, 4);
+                assert_eq!(error.message, "Port not found");
+            },
+            other => panic!("Expected error, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_parse_subscription() {
+        let subscription = Subscription::try_from("A1 1".to_string()).unwrap();
+        assert_eq!(subscription.port_name, "A1");
+        assert!(matches!(subscription.value, RPCReturnParam::Int(1)));
+
+        let subscription = Subscription::try_from("JOY1 -12 40".to_string()).unwrap();
+        assert_eq!(subscription.port_name, "JOY1");
+        assert_eq!(subscription.value.as_string().unwrap(), "-12 40");
+
+        assert!(matches!(Subscription::try_from("A1".to_string()), Err(ProtoError::Malformed(_))));
+    }
+
+    #[test]
+    fn test_trigger_arguments() {
+        let cmd = FtSwarmRPCCommand::deserialize("A1.onTrigger(0, M1, 255)").unwrap();
+        assert!(matches!(&cmd.args[1], Argument::Actor(name) if name == "M1"));
+        assert_eq!(cmd.serialize(), "A1.onTrigger(0, M1, 255)");
+
+        let cmd = FtSwarmRPCCommand {
+            target: "JOY1".to_string(),
+            function: RpcFunction::OnTriggerLR,
+            args: vec![Argument::TriggerEvent(TriggerEvent::Value), Argument::Actor("SERVO1".to_string())],
+        };
+        assert_eq!(cmd.serialize(), "JOY1.onTriggerLR(2, SERVO1)");
+    }
+
+    #[test]
+    fn test_parse_user_event() {
+        let event = match S2RMessage::from("U: 1 -2 3".to_string()) {
+            S2RMessage::UserEvent(event) => UserEvent::try_from(event).unwrap(),
+            other => panic!("Expected user event, got {:?}", other)
+        };
+        assert_eq!(event.params, vec![1, -2, 3]);
+
+        assert!(matches!(UserEvent::try_from("1 x".to_string()), Err(ProtoError::InvalidArgument { .. })));
+        assert!(matches!(UserEvent::try_from("1 2 3 4 5 6 7 8 9 10 11".to_string()), Err(ProtoError::Malformed(_))));
+    }
+
+    #[test]
+    fn test_parse_malformed_user_event() {
+        for line in ["U: 1 two 3", "U: 1 2 3 4 5 6 7 8 9 10 11", "U: 99999999999999999999"] {
+            match S2RMessage::from(line.to_string()) {
+                S2RMessage::UserEvent(event) => assert!(UserEvent::try_from(event).is_err(), "{} was accepted", line),
+                other => panic!("Expected user event, got {:?}", other)
+            }
+        }
+    }
+
+    #[test]
+    fn test_deserialize_arguments() {
+        let cmd = FtSwarmRPCCommand::deserialize("kelda.A1.show(1.5, - 3, true, \"a \\\"b\\\"\", -2e3)").unwrap();
+        assert_eq!(cmd.target, "kelda.A1");
+        assert_eq!(cmd.function, RpcFunction::Show);
+        assert_eq!(cmd.args, vec![
+            Argument::Float(1.5),
+            Argument::Int(-3),
+            Argument::Bool(true),
+            Argument::String("a \"b\"".to_string()),
+            Argument::Float(-2000.0),
+        ]);
+
+        let cmd = FtSwarmRPCCommand::deserialize("A1.setSensorType(2, 1)").unwrap();
+        assert_eq!(cmd.args, vec![Argument::SensorType(SensorType::Switch), Argument::Bool(true)]);
+    }
+
+    fn assert_ids_round_trip<T: IdOf + IntoEnumIterator + TryFrom<i32, Error = ProtoError> + PartialEq + std::fmt::Debug>() {
+        for variant in T::iter() {
+            assert_eq!(T::try_from(variant.id() as i32), Ok(variant));
+        }
+        assert!(matches!(T::try_from(-1), Err(ProtoError::UnknownId { id: -1, .. })));
+    }
+
+    #[test]
+    fn test_enum_ids() {
+        assert_ids_round_trip::<SensorType>();
+        assert_ids_round_trip::<ActorType>();
+        assert_ids_round_trip::<MotionType>();
+        assert_ids_round_trip::<MicroStepMode>();
+        assert_ids_round_trip::<TriggerEvent>();
+        assert_ids_round_trip::<IOType>();
+    }
+
+    fn enum_of<T: IntoEnumIterator + Clone + std::fmt::Debug + 'static>() -> impl Strategy<Value = T> {
+        proptest::sample::select(T::iter().collect::<Vec<_>>())
+    }
+
+    fn name() -> impl Strategy<Value = String> {
+        "[A-Za-z_][A-Za-z0-9_]{0,8}(\\.[A-Za-z_][A-Za-z0-9_]{0,8}){0,2}"
+            .prop_filter("true and false are booleans", |name| name != "true" && name != "false")
+    }
+
+    fn argument(kind: Option<ArgumentKind>) -> BoxedStrategy<Argument> {
+        match kind {
+            Some(ArgumentKind::Int) => any::<i64>().prop_map(Argument::Int).boxed(),
+            Some(ArgumentKind::Float) => proptest::num::f64::NORMAL.prop_map(Argument::Float).boxed(),
+            Some(ArgumentKind::Bool) => any::<bool>().prop_map(Argument::Bool).boxed(),
+            Some(ArgumentKind::ActorType) => enum_of::<ActorType>().prop_map(Argument::ActorType).boxed(),
+            Some(ArgumentKind::SensorType) => enum_of::<SensorType>().prop_map(Argument::SensorType).boxed(),
+            Some(ArgumentKind::MotionType) => enum_of::<MotionType>().prop_map(Argument::MotionType).boxed(),
+            Some(ArgumentKind::MicroStepMode) => enum_of::<MicroStepMode>().prop_map(Argument::MicroStepMode).boxed(),
+            Some(ArgumentKind::TriggerEvent) => enum_of::<TriggerEvent>().prop_map(Argument::TriggerEvent).boxed(),
+            Some(ArgumentKind::Actor) => name().prop_map(Argument::Actor).boxed(),
+            Some(ArgumentKind::String) => any::<String>().prop_map(Argument::String).boxed(),
+            // Without a signature, only the literals can be told apart
+            None => prop_oneof![
+                argument(Some(ArgumentKind::Int)),
+                argument(Some(ArgumentKind::Float)),
+                argument(Some(ArgumentKind::Actor)),
+                argument(Some(ArgumentKind::String)),
+                proptest::num::f64::ZERO.prop_map(Argument::Float),
+            ].boxed(),
+        }
+    }
+
+    fn rpc_command() -> impl Strategy<Value = FtSwarmRPCCommand> {
+        // Custom functions can't be told apart from unknown ones
+        let functions = RpcFunction::iter()
+            .filter(|function| !matches!(function, RpcFunction::Custom(_)))
+            .collect::<Vec<_>>();
+
+        (name(), proptest::sample::select(functions), 0..4usize).prop_flat_map(|(target, function, extra)| {
+            let args = function.signature().iter()
+                .map(|kind| argument(Some(*kind)))
+                .chain((0..extra).map(|_| argument(None)))
+                .collect::<Vec<_>>();
+
+            (Just(target), Just(function), args).prop_map(|(target, function, args)| FtSwarmRPCCommand { target, function, args })
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_rpc_round_trip(cmd in rpc_command()) {
+            prop_assert_eq!(FtSwarmRPCCommand::deserialize(&cmd.serialize()), Ok(cmd));
+        }
+
+        #[test]
+        fn test_argument_round_trip(arg in argument(None)) {
+            prop_assert_eq!(Argument::deserialize(&arg.serialize()), Ok(arg));
+        }
+
+        #[test]
+        fn test_deserialize_never_panics(line in "\\PC*") {
+            let _ = FtSwarmCommand::deserialize(&line);
+        }
+    }
+}
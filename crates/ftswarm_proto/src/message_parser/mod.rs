@@ -1,12 +1,17 @@
+use crate::error::FirmwareError;
+
 pub mod rpc;
 pub mod subscription;
+pub mod user_event;
 
 #[derive(Debug, Clone)]
 pub enum S2RMessage {
     Log(String),
     RPCResponse(String),
     Subscription(String),
-    Error(String),
+    /// A user event raised on the ftSwarm, see [`user_event::UserEvent`]
+    UserEvent(String),
+    Error(FirmwareError),
     StartCLI
 }
 
@@ -22,6 +27,13 @@ fn is_subscription_response(message: &str) -> bool {
     message.starts_with("S: ")
 }
 
+/// User events arrive as `U: P1 P2 .. P10`, with the parameters of `triggerUserEvent(P1,P2,..P10)`
+/// from the firmware's CLI help (`help.out`). The help doesn't show the line itself, the `U: ` prefix
+/// follows the `R: ` and `S: ` lines and isn't verified against the firmware source
+fn is_user_event(message: &str) -> bool {
+    message.starts_with("U: ")
+}
+
 fn is_error_message(message: &str) -> bool {
     message.trim().starts_with("^")
 }
@@ -32,14 +44,16 @@ impl From<String> for S2RMessage {
             return S2RMessage::StartCLI;
         }
 
-        return if is_log_message(&value) {
+        if is_log_message(&value) {
             S2RMessage::Log(value)
         } else if is_rpc_response(&value) {
             S2RMessage::RPCResponse(value.replacen("R: ", "", 1))
         } else if is_subscription_response(&value) {
             S2RMessage::Subscription(value.replacen("S: ", "", 1))
+        } else if is_user_event(&value) {
+            S2RMessage::UserEvent(value.replacen("U: ", "", 1))
         } else if is_error_message(&value) {
-            S2RMessage::Error(value.replacen("^ Error:", "", 1).trim().to_string())
+            S2RMessage::Error(FirmwareError::from(value.as_str()))
         } else {
             S2RMessage::RPCResponse(value)
         }
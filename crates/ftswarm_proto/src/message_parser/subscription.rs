@@ -1,4 +1,5 @@
 use super::rpc::RPCReturnParam;
+use crate::error::ProtoError;
 
 pub struct Subscription {
     pub port_name: String,
@@ -6,14 +7,14 @@ pub struct Subscription {
 }
 
 impl TryFrom<String> for Subscription {
-    type Error = ();
+    type Error = ProtoError;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        // PortName Value
-        let mut parts = value.split_whitespace();
-        let port_name = parts.next().ok_or(())?.to_string();
-        let value = parts.next().ok_or(())?.to_string();
-        let value = RPCReturnParam::from(value);
+        // PortName Value, some ports (e.g. joysticks) report several values separated by spaces
+        let (port_name, payload) = value.trim().split_once(char::is_whitespace)
+            .ok_or(ProtoError::Malformed(format!("No value in subscription {}", value)))?;
+        let port_name = port_name.to_string();
+        let value = RPCReturnParam::from(payload.trim().to_string());
 
         Ok(Subscription { port_name, value })
     }
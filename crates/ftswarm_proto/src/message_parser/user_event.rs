@@ -0,0 +1,29 @@
+use crate::error::ProtoError;
+
+/// The most parameters a user event can carry
+pub const MAX_USER_EVENT_PARAMS: usize = 10;
+
+/// A user event raised by code running on the ftSwarm, sent as `U: P1 P2 .. P10`
+///
+/// **Experimental:** the line format isn't verified against the firmware.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserEvent {
+    pub params: Vec<i64>,
+}
+
+impl TryFrom<String> for UserEvent {
+    type Error = ProtoError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let params = value.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .map(|part| part.parse::<i64>().map_err(|source| ProtoError::InvalidArgument { value: part.to_string(), source }))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if params.len() > MAX_USER_EVENT_PARAMS {
+            return Err(ProtoError::Malformed(format!("Too many parameters in user event {}", value)));
+        }
+
+        Ok(UserEvent { params })
+    }
+}
@@ -0,0 +1,151 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+use tokio::sync::{mpsc as tokio_mpsc, oneshot};
+use crate::{AsyncSwarmSerialPort, SerialError, SwarmSerialPort};
+
+/// How often the port is checked for input right after there was traffic
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// How often the port is checked for input when the connection is idle
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(16);
+
+enum Request {
+    Write(String, oneshot::Sender<Result<(), SerialError>>),
+    WriteBlocking(String, Sender<Result<(), SerialError>>),
+    BlockUntil(String, oneshot::Sender<Result<(), SerialError>>),
+    StartReading,
+}
+
+/// Makes any blocking [`SwarmSerialPort`] usable as an [`AsyncSwarmSerialPort`]
+///
+/// The port is moved to a dedicated thread. Writes wake the thread immediately. Reads are
+/// polled, starting at `MIN_POLL_INTERVAL` after any traffic and backing off to
+/// `MAX_POLL_INTERVAL` while the connection is idle. `write_line` returns once the line was
+/// written to the port. The thread only starts reading once
+/// the first line is requested, so `block_until` still sees the startup output.
+/// It stops when the adapter is dropped or the port fails.
+pub struct BlockingAdapter {
+    requests: Sender<Request>,
+    lines: tokio_mpsc::UnboundedReceiver<Result<String, SerialError>>,
+    reading: bool,
+}
+
+impl BlockingAdapter {
+    pub fn new<Serial: SwarmSerialPort + 'static>(serial_port: Serial) -> Self {
+        let (requests, request_receiver) = mpsc::channel();
+        let (line_sender, lines) = tokio_mpsc::unbounded_channel();
+
+        std::thread::spawn(move || {
+            if let Err(err) = serve(serial_port, request_receiver, &line_sender) {
+                let _ = line_sender.send(Err(err));
+            }
+        });
+
+        BlockingAdapter {
+            requests,
+            lines,
+            reading: false,
+        }
+    }
+
+    fn send(&self, request: Request) -> Result<(), SerialError> {
+        self.requests.send(request).map_err(|_| SerialError::ManualDisconnect)
+    }
+}
+
+/// Writes lines to the thread of a [`BlockingAdapter`] without an async runtime
+///
+/// Used to write a last `halt` while the connection is dropped, when the runtime may not
+/// run any task anymore.
+#[derive(Clone)]
+pub struct BlockingWriter {
+    requests: Sender<Request>,
+}
+
+impl BlockingWriter {
+    /// Write `line`, and wait at most `timeout` until it was written
+    pub fn write_line(&self, line: String, timeout: Duration) -> Result<(), SerialError> {
+        let (sender, receiver) = mpsc::channel();
+        self.requests.send(Request::WriteBlocking(line, sender)).map_err(|_| SerialError::ManualDisconnect)?;
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => Err(SerialError::Timeout),
+            Err(RecvTimeoutError::Disconnected) => Err(SerialError::ManualDisconnect),
+        }
+    }
+}
+
+impl AsyncSwarmSerialPort for BlockingAdapter {
+    async fn read_line(&mut self) -> Result<String, SerialError> {
+        if !self.reading {
+            self.send(Request::StartReading)?;
+            self.reading = true;
+        }
+
+        self.lines.recv().await.unwrap_or(Err(SerialError::ManualDisconnect))
+    }
+
+    async fn write_line(&mut self, line: String) -> Result<(), SerialError> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(Request::Write(line, sender))?;
+        receiver.await.unwrap_or(Err(SerialError::ManualDisconnect))
+    }
+
+    async fn block_until(&mut self, line: String) -> Result<(), SerialError> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(Request::BlockUntil(line, sender))?;
+        receiver.await.unwrap_or(Err(SerialError::ManualDisconnect))
+    }
+
+    fn blocking_writer(&self) -> Option<BlockingWriter> {
+        Some(BlockingWriter { requests: self.requests.clone() })
+    }
+}
+
+fn serve<Serial: SwarmSerialPort>(mut serial_port: Serial, requests: Receiver<Request>, lines: &tokio_mpsc::UnboundedSender<Result<String, SerialError>>) -> Result<(), SerialError> {
+    let mut reading = false;
+    let mut poll_interval = MIN_POLL_INTERVAL;
+
+    loop {
+        let mut idle = true;
+
+        while let Ok(request) = requests.try_recv() {
+            handle(&mut serial_port, request, &mut reading)?;
+            idle = false;
+        }
+
+        while reading && serial_port.available()? {
+            let line = match serial_port.read_line() {
+                Ok(line) => line.replace("\n", "").replace("\r", ""),
+                // Only part of the line arrived yet, the port keeps it for the next call
+                Err(SerialError::Timeout) => break,
+                Err(err) => return Err(err),
+            };
+            if lines.send(Ok(line)).is_err() {
+                return Ok(());
+            }
+            idle = false;
+        }
+
+        poll_interval = if idle { (poll_interval * 2).min(MAX_POLL_INTERVAL) } else { MIN_POLL_INTERVAL };
+
+        match requests.recv_timeout(poll_interval) {
+            Ok(request) => {
+                handle(&mut serial_port, request, &mut reading)?;
+                poll_interval = MIN_POLL_INTERVAL;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+fn handle<Serial: SwarmSerialPort>(serial_port: &mut Serial, request: Request, reading: &mut bool) -> Result<(), SerialError> {
+    match request {
+        Request::Write(line, done) => { let _ = done.send(serial_port.write_line(line)); }
+        Request::WriteBlocking(line, done) => { let _ = done.send(serial_port.write_line(line)); }
+        Request::BlockUntil(line, done) => { let _ = done.send(serial_port.block_until(line)); }
+        Request::StartReading => *reading = true,
+    }
+    Ok(())
+}
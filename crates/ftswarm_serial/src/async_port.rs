@@ -0,0 +1,79 @@
+use std::future::Future;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufStream};
+use log::trace;
+use crate::{BlockingWriter, SerialError};
+
+/// The async counterpart of [`SwarmSerialPort`](crate::SwarmSerialPort)
+///
+/// `read_line` must be cancel safe: if its future is dropped before it completes,
+/// no data may be lost, so it can be used in `tokio::select!`.
+pub trait AsyncSwarmSerialPort: Send {
+    fn read_line(&mut self) -> impl Future<Output=Result<String, SerialError>> + Send;
+    fn write_line(&mut self, line: String) -> impl Future<Output=Result<(), SerialError>> + Send;
+    fn block_until(&mut self, line: String) -> impl Future<Output=Result<(), SerialError>> + Send;
+
+    /// A way to write lines without an async runtime, if the port has its own thread
+    fn blocking_writer(&self) -> Option<BlockingWriter> {
+        None
+    }
+}
+
+/// Line framing for any byte stream, e.g. a serial port or a TCP connection
+pub struct AsyncLinePort<T> {
+    stream: BufStream<T>,
+    line: Vec<u8>,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> AsyncLinePort<T> {
+    pub fn new(stream: T) -> Self {
+        AsyncLinePort {
+            stream: BufStream::new(stream),
+            line: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        self.stream.get_ref()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.stream.into_inner()
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> AsyncSwarmSerialPort for AsyncLinePort<T> {
+    async fn read_line(&mut self) -> Result<String, SerialError> {
+        // Partially read data stays in `self.line`, which makes this cancel safe
+        let read = self.stream.read_until(b'\n', &mut self.line).await?;
+        if read == 0 {
+            return Err(SerialError::ManualDisconnect);
+        }
+
+        let line = std::mem::take(&mut self.line);
+        let str = String::from_utf8(line).map_err(|err| SerialError::EncodingError(Box::new(err)))?;
+        let str = str.trim_end_matches(['\r', '\n']).to_string();
+        trace!("S > R: {}", str);
+        Ok(str)
+    }
+
+    async fn write_line(&mut self, line: String) -> Result<(), SerialError> {
+        trace!("R > S: {}", line);
+        self.stream.write_all(line.as_bytes()).await?;
+        self.stream.write_all(b"\r\n").await?;
+        self.stream.flush().await?;
+
+        Ok(())
+    }
+
+    async fn block_until(&mut self, line: String) -> Result<(), SerialError> {
+        trace!("Blocking until: {}", line);
+        loop {
+            if self.read_line().await?.contains(&line) {
+                break;
+            }
+        }
+
+        trace!("Blocking until: {} - Done", line);
+        Ok(())
+    }
+}
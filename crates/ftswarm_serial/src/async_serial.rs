@@ -0,0 +1,39 @@
+use tokio_serial::SerialStream;
+use crate::async_port::{AsyncLinePort, AsyncSwarmSerialPort};
+use crate::serial::SerialCommunication;
+use crate::SerialError;
+
+/// A serial port that is read and written without blocking a thread
+pub struct AsyncSerialCommunication {
+    port: AsyncLinePort<SerialStream>,
+}
+
+impl AsyncSerialCommunication {
+    /// Open the serial port at `tty`
+    pub fn open(tty: &str) -> Result<Self, SerialError> {
+        let port = SerialStream::open(&tokio_serial::new(tty, 115200))?;
+
+        Ok(AsyncSerialCommunication {
+            port: AsyncLinePort::new(port),
+        })
+    }
+
+    /// Open the first serial port that is available
+    pub fn open_first_available() -> Result<Self, SerialError> {
+        AsyncSerialCommunication::open(&SerialCommunication::get_first_available()?)
+    }
+}
+
+impl AsyncSwarmSerialPort for AsyncSerialCommunication {
+    async fn read_line(&mut self) -> Result<String, SerialError> {
+        self.port.read_line().await
+    }
+
+    async fn write_line(&mut self, line: String) -> Result<(), SerialError> {
+        self.port.write_line(line).await
+    }
+
+    async fn block_until(&mut self, line: String) -> Result<(), SerialError> {
+        self.port.block_until(line).await
+    }
+}
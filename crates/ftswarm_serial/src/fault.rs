@@ -0,0 +1,328 @@
+//! Fault injection for testing retry and recovery code
+//!
+//! [`FaultInjector`] wraps any [`SwarmSerialPort`] and disturbs the lines read from it according to
+//! a [`FaultSchedule`]. The schedule is driven by a seeded generator, so the same seed and the same
+//! traffic always give the same faults.
+//!
+//! ```
+//! use std::time::Duration;
+//! use ftswarm_serial::FixedSerialPort;
+//! use ftswarm_serial::fault::{Fault, FaultInjector, FaultSchedule};
+//!
+//! let schedule = FaultSchedule::seeded(42)
+//!     .drop(0.05)
+//!     .delay(0.1, Duration::from_millis(200))
+//!     .spurious_log(0.1)
+//!     .at(3, Fault::Disconnect);
+//! let port = FaultInjector::new(FixedSerialPort::new(), schedule);
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use log::debug;
+use crate::{SerialError, SwarmSerialPort};
+
+/// A log line that doesn't belong to any request
+pub const SPURIOUS_LOG_LINE: &str = "[fault] injected log line";
+
+/// An error line that doesn't belong to any request
+pub const SPURIOUS_ERROR_LINE: &str = "^ Error: Injected fault";
+
+/// Something that goes wrong with a line read from the port
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// The line and everything after it arrives late
+    Delay(Duration),
+    /// The line is lost
+    Drop,
+    /// A character of the line is garbled
+    Corrupt,
+    /// The end of the line is lost
+    Truncate,
+    /// A log line arrives before the line
+    SpuriousLog,
+    /// An error line that no request caused arrives after the line
+    SpuriousError,
+    /// The connection is lost, every later call fails
+    Disconnect,
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fault::Delay(delay) => write!(f, "delay by {:?}", delay),
+            Fault::Drop => write!(f, "drop"),
+            Fault::Corrupt => write!(f, "corrupt"),
+            Fault::Truncate => write!(f, "truncate"),
+            Fault::SpuriousLog => write!(f, "spurious log line"),
+            Fault::SpuriousError => write!(f, "spurious error line"),
+            Fault::Disconnect => write!(f, "disconnect"),
+        }
+    }
+}
+
+/// Which faults happen to which lines
+///
+/// Lines are counted from 0 in the order they are read from the wrapped port. Faults set with
+/// [`at`](FaultSchedule::at) always happen, the other lines get at most one fault, picked with
+/// the configured probabilities.
+#[derive(Debug, Clone)]
+pub struct FaultSchedule {
+    seed: u64,
+    delay: f64,
+    max_delay: Duration,
+    drop: f64,
+    corrupt: f64,
+    truncate: f64,
+    spurious_log: f64,
+    spurious_error: f64,
+    disconnect: f64,
+    scripted: BTreeMap<usize, Fault>,
+}
+
+impl FaultSchedule {
+    /// A schedule without any faults, whose random faults are picked with `seed`
+    pub fn seeded(seed: u64) -> Self {
+        FaultSchedule {
+            seed,
+            delay: 0.0,
+            max_delay: Duration::ZERO,
+            drop: 0.0,
+            corrupt: 0.0,
+            truncate: 0.0,
+            spurious_log: 0.0,
+            spurious_error: 0.0,
+            disconnect: 0.0,
+            scripted: BTreeMap::new(),
+        }
+    }
+
+    /// Delay lines with `probability`, by up to `max_delay`
+    pub fn delay(mut self, probability: f64, max_delay: Duration) -> Self {
+        self.delay = probability;
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn drop(mut self, probability: f64) -> Self {
+        self.drop = probability;
+        self
+    }
+
+    pub fn corrupt(mut self, probability: f64) -> Self {
+        self.corrupt = probability;
+        self
+    }
+
+    pub fn truncate(mut self, probability: f64) -> Self {
+        self.truncate = probability;
+        self
+    }
+
+    pub fn spurious_log(mut self, probability: f64) -> Self {
+        self.spurious_log = probability;
+        self
+    }
+
+    pub fn spurious_error(mut self, probability: f64) -> Self {
+        self.spurious_error = probability;
+        self
+    }
+
+    pub fn disconnect(mut self, probability: f64) -> Self {
+        self.disconnect = probability;
+        self
+    }
+
+    /// Let `fault` happen to the line with the given index
+    pub fn at(mut self, line: usize, fault: Fault) -> Self {
+        self.scripted.insert(line, fault);
+        self
+    }
+}
+
+/// The faults a [`FaultInjector`] caused, by the index of the line they happened to
+///
+/// Clones share the same entries, so a clone can be kept after the port was handed to an `FtSwarm`.
+#[derive(Debug, Clone, Default)]
+pub struct FaultLog(Arc<Mutex<Vec<(usize, Fault)>>>);
+
+impl FaultLog {
+    pub fn entries(&self) -> Vec<(usize, Fault)> {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn push(&self, line: usize, fault: Fault) {
+        self.0.lock().unwrap().push((line, fault));
+    }
+}
+
+/// SplitMix64, so schedules stay the same regardless of dependency versions
+struct Random(u64);
+
+impl Random {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0.0..1.0`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+struct State<Port> {
+    port: Port,
+    random: Random,
+    /// Lines that were read from the port, with the time they may be passed on
+    pending: VecDeque<(Instant, String)>,
+    lines_read: usize,
+    disconnected: bool,
+}
+
+/// Wraps a [`SwarmSerialPort`] and injects faults into the lines read from it
+pub struct FaultInjector<Port: SwarmSerialPort> {
+    state: Mutex<State<Port>>,
+    schedule: FaultSchedule,
+    log: FaultLog,
+}
+
+impl<Port: SwarmSerialPort> FaultInjector<Port> {
+    pub fn new(port: Port, schedule: FaultSchedule) -> Self {
+        FaultInjector {
+            state: Mutex::new(State {
+                port,
+                random: Random(schedule.seed),
+                pending: VecDeque::new(),
+                lines_read: 0,
+                disconnected: false,
+            }),
+            schedule,
+            log: FaultLog::default(),
+        }
+    }
+
+    /// The faults caused so far
+    pub fn log(&self) -> FaultLog {
+        self.log.clone()
+    }
+
+    /// Pick the fault for the next line, and where in the line a corruption or truncation
+    /// happens. Always draws the same amount of random numbers, so a scripted fault doesn't
+    /// change the faults of later lines
+    fn pick(&self, random: &mut Random, line: usize) -> (Option<Fault>, f64) {
+        let schedule = &self.schedule;
+        let position = random.next_f64();
+        let delay = Duration::from_secs_f64(schedule.max_delay.as_secs_f64() * random.next_f64());
+        let rolls = [
+            (schedule.disconnect, Fault::Disconnect),
+            (schedule.drop, Fault::Drop),
+            (schedule.delay, Fault::Delay(delay)),
+            (schedule.corrupt, Fault::Corrupt),
+            (schedule.truncate, Fault::Truncate),
+            (schedule.spurious_log, Fault::SpuriousLog),
+            (schedule.spurious_error, Fault::SpuriousError),
+        ].map(|(probability, fault)| (random.next_f64() < probability).then_some(fault));
+
+        let fault = schedule.scripted.get(&line).cloned()
+            .or_else(|| rolls.into_iter().flatten().next());
+        (fault, position)
+    }
+
+    /// Move the lines of the wrapped port to `pending`, applying the faults
+    fn fill(&self, state: &mut State<Port>) -> Result<(), SerialError> {
+        while !state.disconnected && state.port.available()? {
+            let mut line = state.port.read_line()?;
+            let index = state.lines_read;
+            state.lines_read += 1;
+
+            let (fault, position) = self.pick(&mut state.random, index);
+            // Lines never overtake each other, so a delay holds back the later lines as well
+            let mut release = state.pending.back().map_or_else(Instant::now, |(release, _)| *release).max(Instant::now());
+
+            if let Some(fault) = &fault {
+                debug!("Injecting fault into line {} ({:?}): {}", index, line, fault);
+                self.log.push(index, fault.clone());
+            }
+
+            match fault {
+                None => {}
+                Some(Fault::Delay(delay)) => release += delay,
+                Some(Fault::Drop) => continue,
+                Some(Fault::Corrupt) => {
+                    let mut chars: Vec<char> = line.chars().collect();
+                    if !chars.is_empty() {
+                        let at = (position * chars.len() as f64) as usize;
+                        chars[at] = '~';
+                    }
+                    line = chars.into_iter().collect();
+                }
+                Some(Fault::Truncate) => {
+                    let at = (position * line.chars().count() as f64) as usize;
+                    line = line.chars().take(at).collect();
+                }
+                Some(Fault::SpuriousLog) => state.pending.push_back((release, SPURIOUS_LOG_LINE.to_string())),
+                Some(Fault::SpuriousError) => {}
+                Some(Fault::Disconnect) => {
+                    state.disconnected = true;
+                    break;
+                }
+            }
+
+            state.pending.push_back((release, line));
+            // After the line, so it can arrive while no request is waiting
+            if fault == Some(Fault::SpuriousError) {
+                state.pending.push_back((release, SPURIOUS_ERROR_LINE.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn disconnected() -> SerialError {
+    SerialError::IoError(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Injected disconnect"))
+}
+
+impl<Port: SwarmSerialPort> SwarmSerialPort for FaultInjector<Port> {
+    fn available(&self) -> Result<bool, SerialError> {
+        let mut state = self.state.lock().unwrap();
+        self.fill(&mut state)?;
+
+        match state.pending.front() {
+            Some((release, _)) => Ok(*release <= Instant::now()),
+            None if state.disconnected => Err(disconnected()),
+            None => Ok(false),
+        }
+    }
+
+    fn read_line(&mut self) -> Result<String, SerialError> {
+        let state = self.state.get_mut().unwrap();
+        match state.pending.pop_front() {
+            Some((release, line)) => {
+                std::thread::sleep(release.saturating_duration_since(Instant::now()));
+                Ok(line)
+            }
+            None if state.disconnected => Err(disconnected()),
+            None => Err(SerialError::Timeout),
+        }
+    }
+
+    fn write_line(&mut self, line: String) -> Result<(), SerialError> {
+        let state = self.state.get_mut().unwrap();
+        if state.disconnected {
+            return Err(disconnected());
+        }
+        state.port.write_line(line)
+    }
+
+    fn block_until(&mut self, line: String) -> Result<(), SerialError> {
+        self.state.get_mut().unwrap().port.block_until(line)
+    }
+}
@@ -1,18 +1,71 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
 pub use mock::FixedSerialPort;
-pub use serial::SerialCommunication;
+pub use serial::{SerialCommunication, UsbId};
+pub use async_port::{AsyncLinePort, AsyncSwarmSerialPort};
+pub use async_serial::AsyncSerialCommunication;
+pub use adapter::{BlockingAdapter, BlockingWriter};
+pub use tcp::TcpCommunication;
+pub use fault::{Fault, FaultInjector, FaultSchedule};
 
 pub mod serial;
 pub mod mock;
+pub mod async_port;
+pub mod async_serial;
+pub mod adapter;
+pub mod tcp;
+pub mod fault;
 
 #[derive(Debug)]
 pub enum SerialError {
-    IoError,
+    /// Reading from or writing to the port failed
+    IoError(std::io::Error),
+    /// The port couldn't be found or opened
+    PortError(serialport::Error),
     Timeout,
     ManualDisconnect,
-    EncodingError,
+    /// A line couldn't be decoded or understood
+    EncodingError(Box<dyn Error + Send + Sync>),
     Other(String),
 }
 
+impl Display for SerialError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SerialError::IoError(err) => write!(f, "I/O error: {}", err),
+            SerialError::PortError(err) => write!(f, "Serial port error: {}", err),
+            SerialError::Timeout => write!(f, "Timed out"),
+            SerialError::ManualDisconnect => write!(f, "Disconnected"),
+            SerialError::EncodingError(err) => write!(f, "Encoding error: {}", err),
+            SerialError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for SerialError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SerialError::IoError(err) => Some(err),
+            SerialError::PortError(err) => Some(err),
+            SerialError::EncodingError(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SerialError {
+    fn from(value: std::io::Error) -> Self {
+        SerialError::IoError(value)
+    }
+}
+
+impl From<serialport::Error> for SerialError {
+    fn from(value: serialport::Error) -> Self {
+        SerialError::PortError(value)
+    }
+}
+
 pub trait SwarmSerialPort: Send {
     fn available(&self) -> Result<bool, SerialError>;
     fn read_line(&mut self) -> Result<String, SerialError>;
@@ -1,9 +1,20 @@
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use crate::{SerialError, SwarmSerialPort};
 
+/// A serial port that answers with a fixed list of responses, in the order they were added.
+///
+/// Like a real controller, it only answers after something was written: every written line
+/// makes the next response readable, except for `subscribe` and `halt`, which the firmware
+/// doesn't answer.
+///
+/// Clones share the same state, so a clone can be kept to inspect the written lines
+/// after the port has been handed to an `FtSwarm`.
+#[derive(Clone)]
 pub struct FixedSerialPort {
-    commands: Mutex<Vec<String>>,
-    initialized: Mutex<bool>,
+    commands: Arc<Mutex<Vec<String>>>,
+    written: Arc<Mutex<Vec<String>>>,
+    released: Arc<Mutex<usize>>,
+    initialized: Arc<Mutex<bool>>,
 }
 
 impl Default for FixedSerialPort {
@@ -15,8 +26,10 @@ impl Default for FixedSerialPort {
 impl FixedSerialPort {
     pub fn new() -> Self {
         FixedSerialPort {
-            commands: Mutex::new(Vec::new()),
-            initialized: Mutex::new(false),
+            commands: Arc::new(Mutex::new(Vec::new())),
+            written: Arc::new(Mutex::new(Vec::new())),
+            released: Arc::new(Mutex::new(0)),
+            initialized: Arc::new(Mutex::new(false)),
         }
     }
 
@@ -25,9 +38,21 @@ impl FixedSerialPort {
         commands.insert(0, response.to_string());
     }
 
+    /// All lines written to this port after the CLI was started, in order
+    pub fn written_lines(&self) -> Vec<String> {
+        self.written.lock().unwrap().clone()
+    }
+
     fn pop_command(&self) -> Option<String> {
+        let mut released = self.released.lock().unwrap();
+        if *released == 0 {
+            return None;
+        }
+
         let mut commands = self.commands.lock().unwrap();
-        commands.pop()
+        let command = commands.pop()?;
+        *released -= 1;
+        Some(command)
     }
 
     fn initialize(&self) {
@@ -41,11 +66,16 @@ impl FixedSerialPort {
     }
 }
 
+fn expects_response(line: &str) -> bool {
+    !line.contains(".subscribe(") && line != "halt"
+}
+
 impl SwarmSerialPort for FixedSerialPort {
     fn available(&self) -> Result<bool, SerialError> {
         if self.is_initialized() {
+            let released = self.released.lock().map_err(|_| SerialError::Other("Mutex error".to_string()))?;
             let commands = self.commands.lock().map_err(|_| SerialError::Other("Mutex error".to_string()))?;
-            Ok(!commands.is_empty())
+            Ok(*released > 0 && !commands.is_empty())
         } else {
             Ok(false)
         }
@@ -58,6 +88,12 @@ impl SwarmSerialPort for FixedSerialPort {
 
     fn write_line(&mut self, line: String) -> Result<(), SerialError> {
         log::debug!("mock write line: {}", line);
+        if self.is_initialized() {
+            if expects_response(&line) {
+                *self.released.lock().map_err(|_| SerialError::Other("Mutex error".to_string()))? += 1;
+            }
+            self.written.lock().map_err(|_| SerialError::Other("Mutex error".to_string()))?.push(line);
+        }
         Ok(())
     }
 
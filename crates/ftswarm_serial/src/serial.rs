@@ -1,9 +1,16 @@
-use serialport::SerialPort;
+use serialport::{SerialPort, SerialPortType};
 use std::thread::sleep;
 use std::io::{Read, Write};
-use log::trace;
+use log::{trace, warn};
 use crate::{SerialError, SwarmSerialPort};
 
+/// The USB vendor and product id of a serial adapter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsbId {
+    pub vid: u16,
+    pub pid: u16,
+}
+
 pub struct SerialCommunication {
     port: Box<dyn SerialPort>,
 }
@@ -15,33 +22,60 @@ impl SerialCommunication {
         }
     }
 
-    pub fn connect(tty: &str) -> Self {
+    /// Open the serial port at `tty`
+    pub fn open(tty: &str) -> Result<Self, SerialError> {
         let port = serialport::new(tty, 115200)
             .timeout(std::time::Duration::from_millis(10))
-            .open()
-            .unwrap_or_else(|_| panic!("Failed to open serial port at {}", tty));
+            .open()?;
 
-        SerialCommunication {
+        Ok(SerialCommunication {
             port
-        }
+        })
+    }
+
+    /// Open the serial port at `tty`, panicking if that fails
+    pub fn connect(tty: &str) -> Self {
+        SerialCommunication::open(tty)
+            .unwrap_or_else(|err| panic!("Failed to open serial port at {}: {}", tty, err))
+    }
+
+    /// Open the first serial port that is available, whatever is connected to it
+    pub fn open_first_available() -> Result<Self, SerialError> {
+        SerialCommunication::open(&SerialCommunication::get_first_available()?)
     }
 
-    pub fn get_first_available() -> Result<String, String> {
-        let ports = serialport::available_ports()
-            .map_err(|_| "No serial ports found")?;
+    pub fn get_first_available() -> Result<String, SerialError> {
+        let ports = serialport::available_ports()?;
 
         if ports.is_empty() {
-            return Err("No serial ports found".to_string());
+            return Err(SerialError::PortError(serialport::Error::new(serialport::ErrorKind::NoDevice, "No serial ports found")));
         }
 
         trace!("Found serial ports: {:?}", ports);
 
         Ok(ports[0].port_name.clone())
     }
+
+    /// The names of all serial ports, or if `usb_ids` isn't empty, of the USB ports with one of these ids
+    pub fn list_ports(usb_ids: &[UsbId]) -> Result<Vec<String>, SerialError> {
+        let ports = serialport::available_ports()?;
+        trace!("Found serial ports: {:?}", ports);
+
+        Ok(ports.into_iter()
+            .filter(|port| usb_ids.is_empty() || match &port.port_type {
+                SerialPortType::UsbPort(info) => usb_ids.contains(&UsbId { vid: info.vid, pid: info.pid }),
+                _ => false,
+            })
+            .map(|port| port.port_name)
+            .collect())
+    }
 }
 
+/// Deprecated: opens the first serial port, whatever is connected to it, and panics if that
+/// fails. Use [`SerialCommunication::open_first_available`] instead
 impl Default for SerialCommunication {
     fn default() -> Self {
+        warn!("SerialCommunication::default is deprecated, use SerialCommunication::open_first_available");
         let tty = SerialCommunication::get_first_available().expect("No serial ports found");
         SerialCommunication::connect(&tty)
     }
@@ -49,28 +83,28 @@ impl Default for SerialCommunication {
 
 impl SwarmSerialPort for SerialCommunication {
     fn available(&self) -> Result<bool, SerialError> {
-        Ok(self.port.bytes_to_read().map_err(|_| SerialError::IoError)? > 0)
+        Ok(self.port.bytes_to_read()? > 0)
     }
 
     fn read_line(&mut self) -> Result<String, SerialError> {
         let mut buffer = Vec::new();
         loop {
             let mut byte = [0];
-            self.port.read_exact(&mut byte).map_err(|_| SerialError::IoError)?;
+            self.port.read_exact(&mut byte)?;
             if byte[0] == b'\n' {
                 break;
             }
             buffer.push(byte[0]);
         }
-        let str = String::from_utf8(buffer).map_err(|_| SerialError::EncodingError)?;
+        let str = String::from_utf8(buffer).map_err(|err| SerialError::EncodingError(Box::new(err)))?;
         trace!("S > R: {}", str);
         Ok(str)
     }
 
     fn write_line(&mut self, line: String) -> Result<(), SerialError> {
         trace!("R > S: {}", line);
-        self.port.write_all(line.as_bytes()).map_err(|_| SerialError::IoError)?;
-        self.port.write_all(b"\r\n").map_err(|_| SerialError::IoError)?;
+        self.port.write_all(line.as_bytes())?;
+        self.port.write_all(b"\r\n")?;
 
         Ok(())
     }
@@ -85,7 +119,7 @@ impl SwarmSerialPort for SerialCommunication {
                 continue;
             }
             let mut byte = [0];
-            self.port.read_exact(&mut byte).map_err(|_| SerialError::IoError)?;
+            self.port.read_exact(&mut byte)?;
 
             if byte[0] == line.as_bytes()[line_pos] {
                 line_pos += 1;
@@ -102,7 +136,7 @@ impl SwarmSerialPort for SerialCommunication {
         if let Ok(t) = self.port.bytes_to_read() {
             if t > 0 {
                 let mut buf = vec![0; t as usize];
-                self.port.read_exact(&mut buf).map_err(|_| SerialError::IoError)?;
+                self.port.read_exact(&mut buf)?;
             }
         }
 
@@ -0,0 +1,168 @@
+use std::io::{ErrorKind, Read};
+use std::mem::MaybeUninit;
+use std::thread::sleep;
+use std::time::Duration;
+use log::trace;
+use socket2::{SockRef, TcpKeepalive};
+use tokio::net::{lookup_host, TcpStream, ToSocketAddrs};
+use tokio::time::Instant;
+use crate::async_port::{AsyncLinePort, AsyncSwarmSerialPort};
+use crate::{SerialError, SwarmSerialPort};
+
+/// How long connecting may take by default
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the connection may be idle before keepalive probes are sent
+const KEEPALIVE_TIME: Duration = Duration::from_secs(5);
+
+/// How long to wait between keepalive probes
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long the blocking interface waits before it tries the socket again
+const BLOCKING_RETRY_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The CLI of an ftSwarm reached over WiFi
+///
+/// Dead connections are found with TCP keepalive, so a controller that lost power or left the
+/// network makes reads fail instead of hanging forever.
+///
+/// It is an [`AsyncSwarmSerialPort`] for `FtSwarm::new_async`, and a [`SwarmSerialPort`] for
+/// `FtSwarm::new`, which reads and writes the socket without the async runtime. Use one of the
+/// two: input buffered by one of them isn't seen by the other.
+pub struct TcpCommunication {
+    port: AsyncLinePort<TcpStream>,
+    /// Bytes read by the blocking interface that don't form a whole line yet
+    received: Vec<u8>,
+}
+
+impl TcpCommunication {
+    pub fn new(stream: TcpStream) -> Result<Self, SerialError> {
+        stream.set_nodelay(true)?;
+
+        let keepalive = TcpKeepalive::new().with_time(KEEPALIVE_TIME);
+        #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+        let keepalive = keepalive.with_interval(KEEPALIVE_INTERVAL);
+        SockRef::from(&stream).set_tcp_keepalive(&keepalive)?;
+
+        Ok(TcpCommunication {
+            port: AsyncLinePort::new(stream),
+            received: Vec::new(),
+        })
+    }
+
+    /// Connect to `address`, a host and port like `("kelda.local", port)`
+    pub async fn connect(address: impl ToSocketAddrs) -> Result<Self, SerialError> {
+        TcpCommunication::connect_timeout(address, DEFAULT_CONNECT_TIMEOUT).await
+    }
+
+    /// Connect to `address`, trying every address it resolves to, for at most `timeout` in total
+    pub async fn connect_timeout(address: impl ToSocketAddrs, timeout: Duration) -> Result<Self, SerialError> {
+        let deadline = Instant::now() + timeout;
+        let addresses = tokio::time::timeout_at(deadline, lookup_host(address)).await
+            .map_err(|_| SerialError::Timeout)??;
+
+        let mut last_error = None;
+        for address in addresses {
+            trace!("Connecting to {}", address);
+            match tokio::time::timeout_at(deadline, TcpStream::connect(address)).await {
+                Ok(Ok(stream)) => return TcpCommunication::new(stream),
+                Ok(Err(err)) => last_error = Some(err),
+                Err(_) => {
+                    last_error = Some(ErrorKind::TimedOut.into());
+                    break;
+                }
+            }
+        }
+
+        Err(match last_error {
+            Some(err) if err.kind() == ErrorKind::TimedOut => SerialError::Timeout,
+            Some(err) => SerialError::IoError(err),
+            None => SerialError::Other("Address didn't resolve to anything".to_string()),
+        })
+    }
+}
+
+impl AsyncSwarmSerialPort for TcpCommunication {
+    async fn read_line(&mut self) -> Result<String, SerialError> {
+        self.port.read_line().await
+    }
+
+    async fn write_line(&mut self, line: String) -> Result<(), SerialError> {
+        self.port.write_line(line).await
+    }
+
+    async fn block_until(&mut self, line: String) -> Result<(), SerialError> {
+        self.port.block_until(line).await
+    }
+}
+
+/// The socket is non-blocking, so reads that find no whole line fail with
+/// [`SerialError::Timeout`] and keep the partial line for the next call, like
+/// [`BlockingAdapter`](crate::BlockingAdapter) expects.
+impl SwarmSerialPort for TcpCommunication {
+    fn available(&self) -> Result<bool, SerialError> {
+        if self.received.contains(&b'\n') {
+            return Ok(true);
+        }
+
+        match SockRef::from(self.port.get_ref()).peek(&mut [MaybeUninit::uninit()]) {
+            // Nothing to peek means the connection was closed, which `read_line` reports
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn read_line(&mut self) -> Result<String, SerialError> {
+        loop {
+            if let Some(end) = self.received.iter().position(|byte| *byte == b'\n') {
+                let line = self.received.drain(..=end).collect();
+                let str = String::from_utf8(line).map_err(|err| SerialError::EncodingError(Box::new(err)))?;
+                let str = str.trim_end_matches(['\r', '\n']).to_string();
+                trace!("S > R: {}", str);
+                return Ok(str);
+            }
+
+            let mut buffer = [0; 1024];
+            match (&*SockRef::from(self.port.get_ref())).read(&mut buffer) {
+                Ok(0) => return Err(SerialError::ManualDisconnect),
+                Ok(read) => self.received.extend_from_slice(&buffer[..read]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Err(SerialError::Timeout),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    fn write_line(&mut self, line: String) -> Result<(), SerialError> {
+        trace!("R > S: {}", line);
+        let data = format!("{}\r\n", line).into_bytes();
+        let socket = SockRef::from(self.port.get_ref());
+
+        let mut written = 0;
+        while written < data.len() {
+            match socket.send(&data[written..]) {
+                Ok(sent) => written += sent,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => sleep(BLOCKING_RETRY_INTERVAL),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn block_until(&mut self, line: String) -> Result<(), SerialError> {
+        trace!("Blocking until: {}", line);
+        loop {
+            match SwarmSerialPort::read_line(self) {
+                Ok(read) if read.contains(&line) => break,
+                Ok(_) => {}
+                Err(SerialError::Timeout) => sleep(BLOCKING_RETRY_INTERVAL),
+                Err(err) => return Err(err),
+            }
+        }
+
+        trace!("Blocking until: {} - Done", line);
+        Ok(())
+    }
+}